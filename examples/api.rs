@@ -1,7 +1,8 @@
-use edsm::api::*;
+use edsm::api::{Client, Show};
 
 fn main() {
-    dbg!(&system("Sol").unwrap());
-    dbg!(&bodies("Sol").unwrap().bodies.as_ref().unwrap()[4].details);
-    dbg!(&factions("LHS 6282", false).unwrap());
+    let client = Client::new();
+    dbg!(&client.system("Sol", Show::default()).unwrap());
+    dbg!(&client.bodies("Sol").unwrap().bodies.as_ref().unwrap()[4].details);
+    dbg!(&client.factions("LHS 6282", false).unwrap());
 }
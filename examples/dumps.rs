@@ -1,9 +1,16 @@
-use edsm::json;
+use edsm::dump::Reader;
 
 fn main() {
-    // XXX: Very slow.
-    // let systems = json("edsm/dumps/systemsWithoutCoordinates.json");
-    let systems = json("tests/systemsPopulated.json");
-    // let systems = json("dumps/systemsPopulated.json");
-    dbg!(&systems[5]);
+    // let path = "dumps/systemsWithoutCoordinates.json";
+    let path = "tests/systemsPopulated.json";
+    let reader: Reader<_> = Reader::open(path).unwrap();
+    for (i, system) in reader.enumerate() {
+        match system {
+            Ok(system) if i == 5 => {
+                dbg!(&system);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
 }
@@ -1,20 +1,147 @@
-use crate::System;
-use reqwest::{StatusCode, Url};
-use serde::Deserialize;
+use self::cache::Layer;
+use self::endpoint::{Exchange, Request};
+use self::journal::JournalReply;
+use self::rate_limit::Limiter;
+use crate::commander::{CreditsReply, FlightLogReply, InventoryReply};
+use crate::search::{Candidate, Search};
+use crate::{
+    Credits, EstimatedValue, FlightLogEntry, InventoryItem, Market, Outfitting,
+    Position, Ranks, Shipyard, System,
+};
+use chrono::NaiveDateTime;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Proxy, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use std::error;
 use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-/// Base URL for the [System (also called Body) API](https://www.edsm.net/api-system-v1)
-pub const SYSTEM_URL: &'static str = "https://www.edsm.net/api-system-v1";
-/// Base URL for the [Systems API](https://www.edsm.net/api-v1)
-pub const SYSTEMS_URL: &'static str = "https://www.edsm.net/api-v1";
+/// Base URL for all of [EDSM's APIs](https://www.edsm.net/en/api)
+pub const DEFAULT_URL: &str = "https://www.edsm.net";
+/// Path of the [System (also called Body) API](https://www.edsm.net/api-system-v1)
+pub const SYSTEM_PATH: &str = "api-system-v1";
+/// Path of the [Systems API](https://www.edsm.net/api-v1)
+pub const SYSTEMS_PATH: &str = "api-v1";
+/// Path of the [Commander API](https://www.edsm.net/en/api-commander-v1)
+pub const COMMANDER_PATH: &str = "api-commander-v1";
+/// Path of the [Logs API](https://www.edsm.net/en/api-logs-v1)
+pub const LOGS_PATH: &str = "api-logs-v1";
+/// Path of the [Journal API](https://www.edsm.net/en/api-journal-v1)
+pub const JOURNAL_PATH: &str = "api-journal-v1";
+/// Path of the [Status API](https://www.edsm.net/en/api-status-v1)
+pub const STATUS_PATH: &str = "api-status-v1";
+
+/// The largest radius EDSM allows for a sphere search, in light years
+pub const MAX_SPHERE_RADIUS: f64 = 100.;
+/// The radius EDSM uses for a sphere search without one, in light years
+pub const DEFAULT_SPHERE_RADIUS: f64 = 50.;
+/// The largest size EDSM allows for a cube search, in light years
+pub const MAX_CUBE_SIZE: f64 = 200.;
+
+// The content type of authenticated requests.
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+mod cache;
+#[cfg(feature = "test-util")]
+pub(crate) use self::cache::key_hash;
+pub use self::cache::{Cache, CachePolicy, DiskCache, Entry, MemoryCache};
+
+mod commander;
+pub use self::commander::{Credentials, Inventory};
+
+mod discard;
+pub use self::discard::{DiscardList, EventName};
+
+mod endpoint;
+
+mod facets;
+pub use self::facets::Facets;
+
+mod journal;
+pub use self::journal::{
+    EventResult, EventStatus, GameState, Journal, UploadError,
+    DEFAULT_BATCH_SIZE,
+};
+
+mod rate_limit;
+pub use self::rate_limit::{RateLimit, Throttle};
+
+mod retry;
+pub use self::retry::RetryPolicy;
+
+mod show;
+pub use self::show::Show;
+
+mod spatial;
+pub use self::spatial::{Center, Shape, SpatialQuery};
+
+mod station;
+pub use self::station::StationRef;
+
+mod status;
+pub use self::status::{ServerStatus, StatusType};
+
+mod systems_query;
+pub use self::systems_query::{Coordinates, SystemsQuery};
+
+#[cfg(feature = "async")]
+mod async_client;
+#[cfg(feature = "async")]
+pub use self::async_client::AsyncClient;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
+    /// EDSM responded with an unexpected HTTP status
     Edsm(StatusCode),
+    /// EDSM responded with `429 Too Many Requests`
+    ///
+    /// The rate limit and `Retry-After` delay are included when EDSM sent them.
+    RateLimited {
+        rate_limit: Option<RateLimit>,
+        retry_after: Option<Duration>,
+    },
+    /// EDSM has no system by the given name
+    ///
+    /// EDSM doesn't use a `404` for this, instead it responds with an empty JSON object or array.
+    NotFound(String),
+    /// EDSM has no station with the given market id or name
+    StationNotFound(StationRef),
+    /// EDSM responded without any body at all
+    Empty,
+    /// EDSM rejected the request with the given `msgnum` and message
+    ///
+    /// The commander APIs respond with `200 OK` even when the credentials are wrong, reporting
+    /// the problem in the body instead.
+    Api {
+        code: u16,
+        message: String,
+    },
+    /// The named commander doesn't share their position publicly
+    PositionNotPublic(String),
+    /// The request needs [`Credentials`], but the client wasn't given any
+    Unauthenticated,
+    /// The client is offline, and the response to this URL isn't cached
+    NotCached(String),
+    /// EDSM's response wasn't the JSON we expected
+    Decode {
+        /// The path to the offending value, like `bodies[3].orbitalPeriod`
+        path: String,
+        /// The part of the body around the error
+        snippet: String,
+        source: serde_json::Error,
+    },
+    /// A parameter was rejected before sending the request
+    InvalidParameter {
+        name: &'static str,
+        reason: String,
+    },
     Request(reqwest::Error),
+    /// The configured base URL can't have API paths appended to it
+    BaseUrl(Url),
 }
 
 impl fmt::Display for Error {
@@ -23,9 +150,41 @@ impl fmt::Display for Error {
             Error::Edsm(s) => {
                 write!(f, "{}", s.canonical_reason().unwrap_or("???"))
             }
+            Error::RateLimited { retry_after, .. } => {
+                write!(f, "rate limited by EDSM")?;
+                if let Some(d) = retry_after {
+                    write!(f, ", retry after {}s", d.as_secs())?;
+                }
+                Ok(())
+            }
+            Error::NotFound(name) => write!(f, "system not found: {}", name),
+            Error::StationNotFound(StationRef::MarketId(id)) => {
+                write!(f, "station not found: market {}", id)
+            }
+            Error::StationNotFound(StationRef::Name { system, station }) => {
+                write!(f, "station not found: {} in {}", station, system)
+            }
+            Error::Empty => write!(f, "empty response"),
+            Error::Api { code, message } => {
+                write!(f, "EDSM error {}: {}", code, message)
+            }
+            Error::PositionNotPublic(name) => {
+                write!(f, "position not public: {}", name)
+            }
+            Error::Unauthenticated => write!(f, "no commander credentials"),
+            Error::NotCached(url) => {
+                write!(f, "offline and not cached: {}", url)
+            }
+            Error::Decode { path, snippet, .. } => {
+                write!(f, "unexpected JSON at `{}` near `{}`", path, snippet)
+            }
+            Error::InvalidParameter { name, reason } => {
+                write!(f, "invalid `{}`: {}", name, reason)
+            }
             // The wrapped error contains additional information and is available
-            // via the source() method.
+            // via the source() method, as is the JSON error of a `Decode`.
             Error::Request(e) => write!(f, "{}", e),
+            Error::BaseUrl(u) => write!(f, "invalid base URL: {}", u),
         }
     }
 }
@@ -33,8 +192,9 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Error::Edsm(_) => None,
+            Error::Decode { ref source, .. } => Some(source),
             Error::Request(ref e) => Some(e),
+            _ => None,
         }
     }
 }
@@ -45,187 +205,965 @@ impl From<reqwest::Error> for Error {
     }
 }
 
-/// Request many [Systems][System] by name
+// EDSM signals most failures with the HTTP status
+fn check_status(status: StatusCode, headers: &HeaderMap) -> Result<()> {
+    if status.is_success() {
+        Ok(())
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        Err(Error::RateLimited {
+            rate_limit: RateLimit::from_headers(headers),
+            retry_after: retry::retry_after(headers),
+        })
+    } else {
+        Err(Error::Edsm(status))
+    }
+}
+
+// Parse a successful response's body
+fn decode<D: DeserializeOwned>(request: &Request, body: &str) -> Result<D> {
+    let trimmed = body.trim();
+    if trimmed.is_empty() {
+        return Err(Error::Empty);
+    }
+    if let Some(e) = request.not_found(trimmed) {
+        return Err(e);
+    }
+    if request.has_msgnum() {
+        check_msgnum(trimmed)?;
+    }
+
+    let deserializer = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let source = e.into_inner();
+        Error::Decode {
+            path,
+            snippet: snippet(body, source.line(), source.column()),
+            source,
+        }
+    })
+}
+
+// EDSM's status for requests answered in the body, `100` means OK
+#[derive(serde::Deserialize)]
+struct Reply {
+    msgnum: u16,
+    msg: String,
+}
+
+fn check_msgnum(body: &str) -> Result<()> {
+    match serde_json::from_str::<Reply>(body) {
+        Ok(reply) if reply.msgnum != 100 => Err(Error::Api {
+            code: reply.msgnum,
+            message: reply.msg,
+        }),
+        // A missing status is reported by decoding the whole body.
+        _ => Ok(()),
+    }
+}
+
+// Up to 40 characters either side of the given (1 based) line and column
+fn snippet(body: &str, line: usize, column: usize) -> String {
+    let line = body.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let chars: Vec<char> = line.chars().collect();
+    let column = column.min(chars.len());
+    let start = column.saturating_sub(40);
+    let end = (column + 40).min(chars.len());
+    chars[start..end].iter().collect()
+}
+
+/// A configurable connection to EDSM
+///
+/// Each `Client` holds its own connection pool, so create one and reuse it (cloning is cheap)
+/// rather than building a new client for every request.
+///
+/// ```no_run
+/// use edsm::api::{Client, Show};
 ///
-/// This function will only return a single system on an exact match.
-pub fn systems(query: &str) -> Result<Vec<System>> {
-    let mut params = vec![("systemName", query)];
+/// let client = Client::new();
+/// let sol = client.system("Sol", Show::default()).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::blocking::Client,
+    base_url: Url,
+    limiter: Arc<Limiter>,
+    retry: RetryPolicy,
+    credentials: Option<Arc<Credentials>>,
+    cache: Option<Layer>,
+}
+
+impl Client {
+    /// Create a client for the public EDSM API with the default settings
+    ///
+    /// # Panics
+    ///
+    /// This panics if the underlying HTTP client can't be initialized, use [`Client::builder`]
+    /// to handle that error instead.
+    pub fn new() -> Client {
+        ClientBuilder::new()
+            .build()
+            .expect("default client configuration")
+    }
 
-    // TODO
-    // // NOTE: `onlyFeatured` cannot be called with `showInformation`
-    // if true {
-    //     params.push(("onlyFeatured", "1"));
-    // }
+    /// Start configuring a new client, see [`ClientBuilder`]
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
 
-    // TODO: only(Un)knownCoordinates
-    // TODO: startDateTime and endDateTime
+    /// The URL every API path is relative to
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
 
-    if true {
-        params.push(("showId", "1"));
+    /// The rate limit EDSM reported with the most recent response
+    ///
+    /// This is shared between clones of this client.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.limiter.last()
     }
-    if true {
-        params.push(("showCoordinates", "1"));
+
+    /// A client for another commander, sharing this client's connections and rate limit
+    pub fn with_credentials(&self, credentials: Credentials) -> Client {
+        Client {
+            credentials: Some(Arc::new(credentials)),
+            ..self.clone()
+        }
     }
-    if true {
-        params.push(("showPermit", "1"));
+
+    /// Request the status of the game's servers
+    pub fn server_status(&self) -> Result<ServerStatus> {
+        self.send(endpoint::server_status()?)
     }
-    if true {
-        params.push(("showInformation", "1"));
+
+    /// Request the [Systems][System] matching a [`SystemsQuery`]
+    pub fn systems_matching(
+        &self,
+        query: &SystemsQuery,
+    ) -> Result<Vec<System>> {
+        self.send(endpoint::systems(query)?)
     }
 
-    let path = format!("{}/systems", SYSTEMS_URL);
-    let url = Url::parse_with_params(&path, &params).unwrap();
-    get(url)
-}
+    /// Request many [Systems][System] by name
+    ///
+    /// This function will only return a single system on an exact match.
+    pub fn systems(&self, query: &str, show: Show) -> Result<Vec<System>> {
+        self.systems_matching(&SystemsQuery::name(query).show(show))
+    }
 
-// TODO: enum for allowing coords as well as systemName.
-pub fn systems_sphere(
-    name: &str,
-    radius: Option<f64>,
-    min_radius: Option<f64>,
-) -> Result<Vec<System>> {
-    let mut params = vec![("systemName", name.to_string())];
+    /// Request many featured [Systems][System] by name
+    ///
+    /// EDSM doesn't allow this with [`Show::information`], so that flag must be off.
+    pub fn featured_systems(
+        &self,
+        query: &str,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let query = SystemsQuery::name(query).featured().show(show);
+        self.systems_matching(&query)
+    }
 
-    if let Some(r) = radius {
-        // TODO: Better error handling.
-        if r > 100. {
-            panic!("radius too large")
+    /// Request the systems named like the [`Search`]'s query, closest first
+    pub fn search(&self, search: &Search) -> Result<Vec<Candidate<System>>> {
+        let query = SystemsQuery::name(search.query.as_str()).show(search.show);
+        Ok(search.rank(self.systems_matching(&query)?))
+    }
+
+    /// Request the [Systems][System] within the region of a [`SpatialQuery`]
+    pub fn systems_within(&self, query: &SpatialQuery) -> Result<Vec<System>> {
+        self.send(endpoint::systems_within(query)?)
+    }
+
+    /// Request the [Systems][System] within a sphere around a [`Center`]
+    ///
+    /// This is shorthand for [`Client::systems_within`] with a [`Shape::Sphere`].
+    pub fn systems_sphere<C: Into<Center>>(
+        &self,
+        center: C,
+        radius: Option<f64>,
+        min_radius: Option<f64>,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let shape = Shape::Sphere { radius, min_radius };
+        self.systems_within(&SpatialQuery::new(center, shape).show(show))
+    }
+
+    /// Request the [Systems][System] within a cube around a [`Center`]
+    ///
+    /// This is shorthand for [`Client::systems_within`] with a [`Shape::Cube`].
+    pub fn systems_cube<C: Into<Center>>(
+        &self,
+        center: C,
+        size: Option<f64>,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let shape = Shape::Cube { size };
+        self.systems_within(&SpatialQuery::new(center, shape).show(show))
+    }
+
+    /// Request a single [System]
+    pub fn system(&self, name: &str, show: Show) -> Result<System> {
+        self.send(endpoint::system(name, &show)?)
+    }
+
+    /// Request a single [System] along with the chosen [`Facets`], merged together
+    ///
+    /// Facets EDSM returns for a different system, like after a rename, are left out.
+    pub fn system_with(
+        &self,
+        name: &str,
+        show: Show,
+        facets: Facets,
+    ) -> Result<System> {
+        let mut system = self.system(name, show)?;
+        // Each facet is fetched after the system, so it's at least as new and its fields
+        // are meant to replace the system's.
+        for request in endpoint::facets(name, &facets)? {
+            let part = self.send(request)?;
+            system.merge(part);
         }
-        params.push(("radius", r.to_string()));
+        Ok(system)
     }
-    if let Some(m) = min_radius {
-        params.push(("minRadius", m.to_string()));
+
+    /// Request a single [System]'s estimated exploration value
+    ///
+    /// The valuable bodies can be matched up with the system's [bodies][Self::bodies] by id.
+    pub fn estimated_value(&self, system_name: &str) -> Result<EstimatedValue> {
+        self.send(endpoint::estimated_value(system_name)?)
     }
 
-    if true {
-        params.push(("showId", "1".into()));
+    /// Request a single [System]'s traffic report
+    pub fn traffic(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::traffic(system_name)?)
     }
-    if true {
-        params.push(("showCoordinates", "1".into()));
+
+    /// Request a single [System]'s death report
+    pub fn deaths(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::deaths(system_name)?)
     }
-    if true {
-        params.push(("showPermit", "1".into()));
+
+    /// Request a single [System] populated with many [Bodies][crate::Body]
+    pub fn bodies(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::bodies(system_name)?)
     }
-    if true {
-        params.push(("showInformation", "1".into()));
+
+    /// Request a single [System] populated with many [Stations][crate::Station]
+    pub fn stations(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::stations(system_name)?)
     }
 
-    let path = format!("{}/sphere-systems", SYSTEMS_URL);
-    let url = Url::parse_with_params(&path, &params).unwrap();
-    get(url)
-}
+    /// Request a station's [Market]
+    pub fn market<S: Into<StationRef>>(&self, station: S) -> Result<Market> {
+        self.send(endpoint::station_service("market", &station.into())?)
+    }
 
-// TODO: enum for allowing coords as well as systemName.
-// TODO: What exactly is `size`?
-pub fn systems_cube(name: &str, size: Option<f64>) -> Result<Vec<System>> {
-    let mut params = vec![("systemName", name.to_string())];
+    /// Request a station's [Shipyard]
+    pub fn shipyard<S: Into<StationRef>>(
+        &self,
+        station: S,
+    ) -> Result<Shipyard> {
+        self.send(endpoint::station_service("shipyard", &station.into())?)
+    }
 
-    if let Some(s) = size {
-        params.push(("size", s.to_string()));
+    /// Request a station's [Outfitting]
+    pub fn outfitting<S: Into<StationRef>>(
+        &self,
+        station: S,
+    ) -> Result<Outfitting> {
+        self.send(endpoint::station_service("outfitting", &station.into())?)
     }
 
-    if true {
-        params.push(("showId", "1".into()));
+    /// Fetch a system with it's factions
+    ///
+    /// Passing a value of `true` for `history` will populate the appropriate history structures
+    /// within each faction.
+    pub fn factions(&self, system_name: &str, history: bool) -> Result<System> {
+        self.send(endpoint::factions(system_name, history)?)
     }
-    if true {
-        params.push(("showCoordinates", "1".into()));
+
+    /// Request the commander's [Ranks]
+    pub fn ranks(&self) -> Result<Ranks> {
+        self.send(endpoint::ranks(self.credentials()?)?)
     }
-    if true {
-        params.push(("showPermit", "1".into()));
+
+    /// Request the commander's current credit balance
+    pub fn credits(&self) -> Result<Vec<Credits>> {
+        let reply: CreditsReply =
+            self.send(endpoint::credits(self.credentials()?)?)?;
+        Ok(reply.credits)
     }
-    if true {
-        params.push(("showInformation", "1".into()));
+
+    /// Request the commander's materials, data or cargo
+    pub fn inventory(
+        &self,
+        inventory: Inventory,
+    ) -> Result<Vec<InventoryItem>> {
+        let request = endpoint::inventory(self.credentials()?, inventory)?;
+        let reply: InventoryReply = self.send(request)?;
+        Ok(reply.materials)
     }
 
-    let path = format!("{}/cube-systems", SYSTEMS_URL);
-    let url = Url::parse_with_params(&path, &params).unwrap();
-    get(url)
-}
+    /// Request the commander's last known [Position]
+    pub fn position(&self) -> Result<Position> {
+        self.send(endpoint::position(self.credentials()?)?)
+    }
 
-// TODO: unify both sphere and cube functions.
+    /// Request the [System] the commander was last seen in
+    ///
+    /// This is [`PositionNotPublic`][Error::PositionNotPublic] when the commander hides it.
+    pub fn commander_system(&self, show: Show) -> Result<System> {
+        match self.position()?.system {
+            Some(name) => self.system(&name, show),
+            None => Err(Error::PositionNotPublic(
+                self.credentials()?.commander_name().into(),
+            )),
+        }
+    }
 
-/// Request a single [System]
-pub fn system(name: &str) -> Result<System> {
-    let mut params = vec![("systemName", name)];
-    if true {
-        // sets `id` and `id64`
-        params.push(("showId", "1"));
+    /// Request the commander's flight log
+    ///
+    /// Without a start and end EDSM returns the last week, and longer ranges are cut down to a
+    /// week.
+    pub fn flight_log(
+        &self,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Result<Vec<FlightLogEntry>> {
+        let request = endpoint::flight_log(self.credentials()?, start, end)?;
+        let reply: FlightLogReply = self.send(request)?;
+        Ok(reply.logs)
     }
-    if true {
-        // sets `coords`
-        params.push(("showCoordinates", "1"));
+
+    /// Request the names of the journal events EDSM doesn't want uploaded
+    pub fn discarded_events(&self) -> Result<DiscardList> {
+        self.send(endpoint::discard()?)
     }
-    if true {
-        // sets `require_permit` and `permit_name`
-        params.push(("showPermit", "1"));
+
+    /// Upload a [Journal]'s pending events, in batches
+    ///
+    /// The results are in the same order as the events. Events EDSM responded to are removed
+    /// from the journal even if they failed, so on an error only the remaining events are
+    /// pending, and the results of those already uploaded are part of the [`UploadError`].
+    pub fn upload_journal(
+        &self,
+        journal: &mut Journal,
+    ) -> std::result::Result<Vec<EventResult>, UploadError> {
+        let credentials = self.credentials().map_err(|error| UploadError {
+            results: Vec::new(),
+            error,
+        })?;
+        let mut results = Vec::new();
+        while let Some(batch) = journal.batch() {
+            let count = batch.len();
+            let reply: Result<JournalReply> =
+                endpoint::journal(credentials, journal)
+                    .and_then(|request| self.send(request));
+            match reply {
+                Ok(reply) => {
+                    journal.sent(count);
+                    results.extend(reply.events);
+                }
+                Err(error) => return Err(UploadError { results, error }),
+            }
+        }
+        Ok(results)
     }
-    if true {
-        // sets `allegiance`, `government`, `faction`, `faction_state`, `population`, `security`,
-        // and `economy`
-        params.push(("showInformation", "1"));
+
+    fn credentials(&self) -> Result<&Credentials> {
+        self.credentials.as_deref().ok_or(Error::Unauthenticated)
     }
 
-    let path = format!("{}/system", SYSTEMS_URL);
-    let url = Url::parse_with_params(&path, &params).unwrap();
-    get(url)
-    // TODO: add option to call bodies and merge, then remove `bodies` function.
+    // Get the JSON resource for the given request
+    fn send<D: DeserializeOwned>(&self, request: Request) -> Result<D> {
+        let mut exchange = Exchange::new(
+            request,
+            &self.base_url,
+            &self.limiter,
+            &self.retry,
+            self.cache.as_ref(),
+        );
+        if let Some(cached) = exchange.cached()? {
+            return Ok(cached);
+        }
+        loop {
+            let wait = exchange.throttle();
+            if !wait.is_zero() {
+                thread::sleep(wait);
+            }
+
+            let url = exchange.url().clone();
+            let http = match exchange.form() {
+                Some(form) => self
+                    .http
+                    .post(url)
+                    .header(CONTENT_TYPE, FORM_CONTENT_TYPE)
+                    .body(form.to_owned()),
+                None => self.http.get(url),
+            };
+            let delay = match http.send() {
+                Ok(response) => {
+                    let status = response.status();
+                    match exchange.response(status, response.headers())? {
+                        Some(delay) => delay,
+                        // The connection can still drop while reading the body.
+                        None => match response.text() {
+                            Ok(body) => return exchange.finish(&body),
+                            Err(e) => exchange.error(e)?,
+                        },
+                    }
+                }
+                Err(e) => exchange.error(e)?,
+            };
+            thread::sleep(delay);
+        }
+    }
 }
 
-// TODO: fn /estimated-value
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
 
-/// Request a single [System]'s traffic report
-pub fn traffic(system_name: &str) -> Result<System> {
-    let url = Url::parse_with_params(
-        &format!("{}/traffic", SYSTEM_URL),
-        &[("systemName", system_name)],
-    )
-    .unwrap();
-    get(url)
+/// Configuration for a [`Client`]
+///
+/// ```no_run
+/// use edsm::api::Client;
+/// use std::time::Duration;
+///
+/// let client = Client::builder()
+///     .base_url("http://localhost:8080".parse().unwrap())
+///     .timeout(Duration::from_secs(10))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: Url,
+    user_agent: String,
+    timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    throttle: Option<Throttle>,
+    retry: RetryPolicy,
+    credentials: Option<Credentials>,
+    cache: Option<Arc<dyn Cache>>,
+    cache_policy: CachePolicy,
+    offline: bool,
 }
 
-/// Request a single [System]'s death report
-pub fn deaths(system_name: &str) -> Result<System> {
-    let url = Url::parse_with_params(
-        &format!("{}/deaths", SYSTEM_URL),
-        &[("systemName", system_name)],
-    )
-    .unwrap();
-    get(url)
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        ClientBuilder {
+            base_url: DEFAULT_URL.parse().expect("valid default URL"),
+            user_agent: concat!("edsm/", env!("CARGO_PKG_VERSION")).into(),
+            timeout: None,
+            proxy: None,
+            throttle: None,
+            retry: RetryPolicy::none(),
+            credentials: None,
+            cache: None,
+            cache_policy: CachePolicy::default(),
+            offline: false,
+        }
+    }
+
+    /// Send requests to another EDSM compatible server, like a local mirror
+    pub fn base_url(mut self, url: Url) -> ClientBuilder {
+        self.base_url = url;
+        self
+    }
+
+    /// Set the `User-Agent` header, defaults to `edsm/<version>`
+    pub fn user_agent<S: Into<String>>(
+        mut self,
+        user_agent: S,
+    ) -> ClientBuilder {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Limit how long each request may take in total
+    ///
+    /// By default a [`Client`]'s requests time out after 30 seconds, while an `AsyncClient`'s
+    /// never do.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Route all requests through the given proxy
+    pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Wait as needed before each request to stay within the given [`Throttle`]
+    ///
+    /// Without a throttle requests are sent immediately, and going over EDSM's rate limit
+    /// results in an error.
+    pub fn throttle(mut self, throttle: Throttle) -> ClientBuilder {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Retry failed requests according to the given policy
+    ///
+    /// By default requests are never retried, see [`RetryPolicy::default`] for a reasonable
+    /// policy to use instead.
+    pub fn retry(mut self, retry: RetryPolicy) -> ClientBuilder {
+        self.retry = retry;
+        self
+    }
+
+    /// Authenticate as a commander, enabling the commander and flight log APIs
+    pub fn credentials(mut self, credentials: Credentials) -> ClientBuilder {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Keep responses in the given [`Cache`], reusing them while they're fresh
+    pub fn cache<C: Cache + 'static>(mut self, cache: C) -> ClientBuilder {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Decide how long cached responses are fresh, see [`CachePolicy::default`]
+    pub fn cache_policy(mut self, policy: CachePolicy) -> ClientBuilder {
+        self.cache_policy = policy;
+        self
+    }
+
+    /// Never contact EDSM, only respond with cached data, no matter how old
+    ///
+    /// Anything not in the cache is a [`NotCached`][Error::NotCached] error.
+    pub fn offline(mut self) -> ClientBuilder {
+        self.offline = true;
+        self
+    }
+
+    // The cache layer, offline without a cache nothing can be found.
+    fn layer(&mut self) -> Option<Layer> {
+        let store = match self.cache.take() {
+            Some(store) => store,
+            None if self.offline => Arc::new(MemoryCache::new(0)),
+            None => return None,
+        };
+        let policy = std::mem::take(&mut self.cache_policy);
+        Some(Layer::new(store, policy, self.offline))
+    }
+
+    pub fn build(mut self) -> Result<Client> {
+        if self.base_url.cannot_be_a_base() {
+            return Err(Error::BaseUrl(self.base_url));
+        }
+        let cache = self.layer();
+
+        let mut http =
+            reqwest::blocking::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            http = http.proxy(proxy);
+        }
+
+        Ok(Client {
+            http: http.build()?,
+            base_url: self.base_url,
+            limiter: Arc::new(Limiter::new(self.throttle)),
+            retry: self.retry,
+            credentials: self.credentials.map(Arc::new),
+            cache,
+        })
+    }
+
+    /// Build an [`AsyncClient`] with this configuration instead
+    #[cfg(feature = "async")]
+    pub fn build_async(mut self) -> Result<AsyncClient> {
+        if self.base_url.cannot_be_a_base() {
+            return Err(Error::BaseUrl(self.base_url));
+        }
+        let cache = self.layer();
+
+        let mut http = reqwest::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            http = http.proxy(proxy);
+        }
+
+        Ok(AsyncClient {
+            http: http.build()?,
+            base_url: self.base_url,
+            limiter: Arc::new(Limiter::new(self.throttle)),
+            retry: self.retry,
+            credentials: self.credentials.map(Arc::new),
+            cache,
+        })
+    }
 }
 
-/// Request a single [System] populated with many [Bodies][crate::Body]
-pub fn bodies(system_name: &str) -> Result<System> {
-    let url = Url::parse_with_params(
-        &format!("{}/bodies", SYSTEM_URL),
-        &[("systemName", system_name)],
-    )
-    .unwrap();
-    get(url)
+impl Default for ClientBuilder {
+    fn default() -> ClientBuilder {
+        ClientBuilder::new()
+    }
 }
 
-/// Fetch a system with it's factions
-///
-/// Passing a value of `true` for `history` will populate the appropriate history structures within
-/// each faction.
-pub fn factions(system_name: &str, history: bool) -> Result<System> {
-    let url = Url::parse_with_params(
-        &format!("{}/factions", SYSTEM_URL),
-        &[
-            ("systemName", system_name),
-            ("showHistory", &(history as u8).to_string()),
-        ],
-    )
-    .unwrap();
-    get(url)
-}
-
-// Get a JSON resource from the given URL
-fn get<D: for<'de> Deserialize<'de>>(url: Url) -> Result<D> {
-    let response = reqwest::blocking::get(url)?;
-    let status = response.status();
-    if status.is_success() {
-        response.json::<D>().map_err(|e| e.into())
-    } else {
-        Err(Error::Edsm(status))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use elite_journal::prelude::Coordinate;
+
+    #[test]
+    fn url_under_base_path() {
+        let base = "http://localhost:8080/edsm/".parse().unwrap();
+        let url = endpoint::traffic("Sol").unwrap().url(&base);
+        assert_eq!(
+            "http://localhost:8080/edsm/api-system-v1/traffic?systemName=Sol",
+            url.as_str()
+        );
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let sphere = |radius, min_radius| {
+            let shape = Shape::Sphere { radius, min_radius };
+            endpoint::systems_within(&SpatialQuery::new("Sol", shape))
+        };
+        let cube = |size| {
+            let shape = Shape::Cube { size };
+            endpoint::systems_within(&SpatialQuery::new("Sol", shape))
+        };
+        assert!(endpoint::system("", &Show::default()).is_err());
+        assert!(endpoint::factions("  ", false).is_err());
+        assert!(sphere(Some(-1.), None).is_err());
+        assert!(sphere(Some(f64::NAN), None).is_err());
+        assert!(sphere(None, Some(100.5)).is_err());
+        assert!(sphere(None, Some(60.)).is_err());
+        assert!(sphere(None, Some(50.)).is_ok());
+        assert!(sphere(Some(5.), Some(5.)).is_ok());
+        assert!(cube(Some(200.)).is_ok());
+        assert!(cube(Some(-5.)).is_err());
+        assert!(cube(Some(150.)).is_ok());
+        let query = SpatialQuery::sphere("Sol").radius(5.).size(150.);
+        assert!(endpoint::systems_within(&query).is_ok());
+        let query = SpatialQuery::cube("Sol").radius(150.);
+        assert!(endpoint::systems_within(&query).is_err());
+    }
+
+    #[test]
+    fn center_coords() {
+        let base = DEFAULT_URL.parse().unwrap();
+        let coords = Coordinate {
+            x: 1.5,
+            y: -2.,
+            z: 3.,
+        };
+        let query = SpatialQuery::cube(coords);
+        let request = endpoint::systems_within(&query).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-v1/cube-systems?x=1.5&y=-2&z=3&showId=1&showCoordinates=1&showPermit=1&showInformation=1",
+            request.url(&base).as_str()
+        );
+        assert!(request.not_found("{}").is_none());
+    }
+
+    #[test]
+    fn show_flags() {
+        let base = DEFAULT_URL.parse().unwrap();
+        let show = Show {
+            primary_star: true,
+            ..Show::none()
+        };
+        let request = endpoint::system("Sol", &show).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-v1/system?systemName=Sol&showPrimaryStar=1",
+            request.url(&base).as_str()
+        );
+        let featured = SystemsQuery::name("Sol").featured();
+        assert!(endpoint::systems(&featured.show(Show::none())).is_ok());
+        let featured = SystemsQuery::name("Sol").featured();
+        assert!(endpoint::systems(&featured.show(Show::all())).is_err());
+    }
+
+    #[test]
+    fn systems_query() {
+        let base = DEFAULT_URL.parse().unwrap();
+        let start = NaiveDate::from_ymd_opt(3307, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 4, 5)
+            .unwrap();
+        let query = SystemsQuery::names(vec!["Sol", "Achenar"])
+            .since(start)
+            .unknown_coordinates()
+            .show(Show::none());
+        let request = endpoint::systems(&query).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-v1/systems?systemName%5B%5D=Sol&systemName%5B%5D=Achenar&onlyUnknownCoordinates=1&startDateTime=3307-01-02+03%3A04%3A05",
+            request.url(&base).as_str()
+        );
+        let request =
+            endpoint::systems(&SystemsQuery::names(vec!["Sol"])).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-v1/systems?systemName%5B%5D=Sol&showId=1&showCoordinates=1&showPermit=1&showInformation=1",
+            request.url(&base).as_str()
+        );
+        let request = endpoint::systems(&SystemsQuery::name("Sol")).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-v1/systems?systemName=Sol&showId=1&showCoordinates=1&showPermit=1&showInformation=1",
+            request.url(&base).as_str()
+        );
+        assert!(endpoint::systems(&SystemsQuery::new()).is_err());
+        assert!(endpoint::systems(&SystemsQuery::new().until(start)).is_ok());
+        let backwards = SystemsQuery::new().since(start).until(start);
+        assert!(endpoint::systems(&backwards).is_ok());
+        let backwards = backwards.since(start + chrono::Duration::seconds(1));
+        assert!(endpoint::systems(&backwards).is_err());
+    }
+
+    #[test]
+    fn station_ref() {
+        let base = DEFAULT_URL.parse().unwrap();
+        let by_id = StationRef::from(128106744);
+        let request = endpoint::station_service("market", &by_id).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-system-v1/stations/market?marketId=128106744",
+            request.url(&base).as_str()
+        );
+        let by_name = StationRef::from(("Sol", "Daedalus"));
+        let request = endpoint::station_service("shipyard", &by_name).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-system-v1/stations/shipyard?systemName=Sol&stationName=Daedalus",
+            request.url(&base).as_str()
+        );
+        let nameless = StationRef::from(("Sol", ""));
+        assert!(endpoint::station_service("outfitting", &nameless).is_err());
+    }
+
+    #[test]
+    fn credentials() {
+        let credentials = Credentials::new("Jameson", "secret-key");
+        assert!(!format!("{:?}", credentials).contains("secret-key"));
+
+        let base = DEFAULT_URL.parse().unwrap();
+        let request = endpoint::ranks(&credentials).unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-commander-v1/get-ranks",
+            request.url(&base).as_str()
+        );
+        assert_eq!(
+            Some("commanderName=Jameson&apiKey=secret-key".into()),
+            request.form()
+        );
+
+        let client = ClientBuilder::new().build().unwrap();
+        assert!(matches!(client.ranks(), Err(Error::Unauthenticated)));
+        let nameless = client.with_credentials(Credentials::new("", "key"));
+        assert!(matches!(
+            nameless.ranks(),
+            Err(Error::InvalidParameter {
+                name: "commanderName",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn journal_request() {
+        let credentials = Credentials::new("Jameson", "key");
+        let mut journal = Journal::new("test", "1.0");
+        journal
+            .push(&serde_json::json!({ "event": "Fileheader", "build": "r1" }))
+            .unwrap();
+        let request = endpoint::journal(&credentials, &journal).unwrap();
+        let base = DEFAULT_URL.parse().unwrap();
+        assert_eq!(
+            "https://www.edsm.net/api-journal-v1",
+            request.url(&base).as_str()
+        );
+        let form = request.form().unwrap();
+        assert!(form.contains("fromSoftware=test&fromSoftwareVersion=1.0"));
+        assert!(form.contains("fromGameBuild=r1"));
+        assert!(form.contains("message=%5B%7B%22build"));
+    }
+
+    #[test]
+    fn offline_cache() {
+        let base: Url = DEFAULT_URL.parse().unwrap();
+        let request = endpoint::system("Sol", &Show::default()).unwrap();
+        let cache = MemoryCache::new(1);
+        cache.put(
+            request.url(&base).as_str(),
+            Entry {
+                body: r#"{ "name": "Sol" }"#.into(),
+                stored: std::time::UNIX_EPOCH,
+            },
+        );
+        let client =
+            ClientBuilder::new().cache(cache).offline().build().unwrap();
+        let sol = client.system("Sol", Show::default()).unwrap();
+        assert_eq!("Sol", sol.name);
+        assert!(matches!(
+            client.system("Achenar", Show::default()),
+            Err(Error::NotCached(_))
+        ));
+
+        let uncached = ClientBuilder::new().offline().build().unwrap();
+        assert!(matches!(
+            uncached.system("Sol", Show::default()),
+            Err(Error::NotCached(_))
+        ));
+    }
+
+    #[test]
+    fn undecodable_cache() {
+        // Nothing listens on the discard port, so requests fail fast.
+        let base: Url = "http://127.0.0.1:9".parse().unwrap();
+        let request = endpoint::system("Sol", &Show::default()).unwrap();
+        let cache = || {
+            let cache = MemoryCache::new(1);
+            cache.put(
+                request.url(&base).as_str(),
+                Entry {
+                    body: r#"{ "id": "27" }"#.into(),
+                    stored: std::time::SystemTime::now(),
+                },
+            );
+            cache
+        };
+        let builder = || {
+            ClientBuilder::new()
+                .base_url(base.clone())
+                .retry(RetryPolicy::none())
+        };
+
+        let offline = builder().cache(cache()).offline().build().unwrap();
+        assert!(matches!(
+            offline.system("Sol", Show::default()),
+            Err(Error::Decode { .. })
+        ));
+        // Online the entry is a miss, so EDSM is asked instead.
+        let online = builder().cache(cache()).build().unwrap();
+        assert!(matches!(
+            online.system("Sol", Show::default()),
+            Err(Error::Request(_))
+        ));
+    }
+
+    #[test]
+    fn decode_msgnum() {
+        let credentials = Credentials::new("Jameson", "wrong-key");
+        let request = endpoint::ranks(&credentials).unwrap();
+        let body = r#"{"msgnum":203,"msg":"Commander name/API Key not found"}"#;
+        assert!(matches!(
+            decode::<Ranks>(&request, body),
+            Err(Error::Api { code: 203, .. })
+        ));
+    }
+
+    #[test]
+    fn decode_not_found() {
+        let request = endpoint::system("Nope", &Show::default()).unwrap();
+        for body in &["{}", "[]", " {}\n"] {
+            let result = decode::<System>(&request, body);
+            assert!(matches!(result, Err(Error::NotFound(n)) if n == "Nope"));
+        }
+        let query = SpatialQuery::sphere("Sol").radius(1.).min_radius(0.5);
+        let request = endpoint::systems_within(&query).unwrap();
+        let systems = decode::<Vec<System>>(&request, "[]").unwrap();
+        assert!(systems.is_empty());
+
+        let request =
+            endpoint::station_service("market", &128106744.into()).unwrap();
+        let result = decode::<Market>(&request, "{}");
+        assert!(matches!(
+            result,
+            Err(Error::StationNotFound(StationRef::MarketId(128106744)))
+        ));
+        assert_eq!(
+            "station not found: market 128106744",
+            result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn decode_empty() {
+        let request = endpoint::system("Sol", &Show::default()).unwrap();
+        let result = decode::<System>(&request, "");
+        assert!(matches!(result, Err(Error::Empty)));
+    }
+
+    #[test]
+    fn decode_error_path() {
+        let request = endpoint::traffic("Sol").unwrap();
+        let body = r#"{"name":"Sol","traffic":{"total":1,"week":"x","day":1}}"#;
+        match decode::<System>(&request, body) {
+            Err(Error::Decode { path, snippet, .. }) => {
+                assert_eq!("traffic.week", path);
+                assert!(snippet.contains(r#""week":"x""#));
+            }
+            other => panic!("expected decode error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rate_limited() {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "5".parse().unwrap());
+        let result = check_status(StatusCode::TOO_MANY_REQUESTS, &headers);
+        assert!(matches!(
+            result,
+            Err(Error::RateLimited {
+                rate_limit: None,
+                retry_after: Some(d),
+            }) if d == Duration::from_secs(5)
+        ));
+    }
+
+    #[test]
+    fn invalid_base_url() {
+        let result = Client::builder()
+            .base_url("mailto:edsm@example.com".parse().unwrap())
+            .build();
+        assert!(matches!(result, Err(Error::BaseUrl(_))));
+    }
+
+    #[test]
+    fn retry_truncated_body() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bodies = [
+            r#"{ "status": "#,
+            r#"{ "status": 2, "type": "success", "message": "OK" }"#,
+        ];
+        let server = thread::spawn(move || {
+            // The first response promises more than it sends.
+            for (body, length) in bodies.iter().zip([100, bodies[1].len()]) {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0; 1024]);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    length, body
+                )
+                .unwrap();
+            }
+        });
+        let client =
+            Client::builder()
+                .base_url(format!("http://{}", addr).parse().unwrap())
+                .retry(RetryPolicy::default().backoff(
+                    Duration::from_millis(1),
+                    Duration::from_millis(1),
+                ))
+                .build()
+                .unwrap();
+        assert_eq!(2, client.server_status().unwrap().status);
+        server.join().unwrap();
     }
 }
@@ -0,0 +1,403 @@
+use super::cache::Layer;
+use super::endpoint::{self, Exchange, Request};
+use super::journal::JournalReply;
+use super::rate_limit::Limiter;
+use super::{
+    Center, ClientBuilder, Credentials, DiscardList, Error, EventResult,
+    Facets, Inventory, Journal, RateLimit, Result, RetryPolicy, ServerStatus,
+    Shape, Show, SpatialQuery, StationRef, SystemsQuery, UploadError,
+    FORM_CONTENT_TYPE,
+};
+use crate::commander::{CreditsReply, FlightLogReply, InventoryReply};
+use crate::search::{Candidate, Search};
+use crate::{
+    Credits, EstimatedValue, FlightLogEntry, InventoryItem, Market, Outfitting,
+    Position, Ranks, Shipyard, System,
+};
+use chrono::NaiveDateTime;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// An async counterpart to [`Client`][super::Client]
+///
+/// Every endpoint has the same name, arguments and result as on the blocking client, but returns
+/// a future instead. Build one with [`ClientBuilder::build_async`], or use
+/// [`AsyncClient::new`] for the default configuration.
+///
+/// ```no_run
+/// # async fn run() -> edsm::api::Result<()> {
+/// use edsm::api::{AsyncClient, Show};
+///
+/// let client = AsyncClient::new();
+/// let sol = client.system("Sol", Show::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+    pub(super) http: reqwest::Client,
+    pub(super) base_url: Url,
+    pub(super) limiter: Arc<Limiter>,
+    pub(super) retry: RetryPolicy,
+    pub(super) credentials: Option<Arc<Credentials>>,
+    pub(super) cache: Option<Layer>,
+}
+
+impl AsyncClient {
+    /// Create a client for the public EDSM API with the default settings
+    ///
+    /// # Panics
+    ///
+    /// This panics if the underlying HTTP client can't be initialized, use
+    /// [`ClientBuilder::build_async`] to handle that error instead.
+    pub fn new() -> AsyncClient {
+        ClientBuilder::new()
+            .build_async()
+            .expect("default client configuration")
+    }
+
+    /// The URL every API path is relative to
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The rate limit EDSM reported with the most recent response
+    ///
+    /// This is shared between clones of this client.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.limiter.last()
+    }
+
+    /// A client for another commander, sharing this client's connections and rate limit
+    pub fn with_credentials(&self, credentials: Credentials) -> AsyncClient {
+        AsyncClient {
+            credentials: Some(Arc::new(credentials)),
+            ..self.clone()
+        }
+    }
+
+    /// Request the status of the game's servers
+    pub async fn server_status(&self) -> Result<ServerStatus> {
+        self.send(endpoint::server_status()?).await
+    }
+
+    /// Request the [Systems][System] matching a [`SystemsQuery`]
+    pub async fn systems_matching(
+        &self,
+        query: &SystemsQuery,
+    ) -> Result<Vec<System>> {
+        self.send(endpoint::systems(query)?).await
+    }
+
+    /// Request many [Systems][System] by name
+    ///
+    /// This function will only return a single system on an exact match.
+    pub async fn systems(
+        &self,
+        query: &str,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        self.systems_matching(&SystemsQuery::name(query).show(show))
+            .await
+    }
+
+    /// Request many featured [Systems][System] by name
+    ///
+    /// EDSM doesn't allow this with [`Show::information`], so that flag must be off.
+    pub async fn featured_systems(
+        &self,
+        query: &str,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let query = SystemsQuery::name(query).featured().show(show);
+        self.systems_matching(&query).await
+    }
+
+    /// Request the systems named like the [`Search`]'s query, closest first
+    pub async fn search(
+        &self,
+        search: &Search,
+    ) -> Result<Vec<Candidate<System>>> {
+        let query = SystemsQuery::name(search.query.as_str()).show(search.show);
+        Ok(search.rank(self.systems_matching(&query).await?))
+    }
+
+    /// Request the [Systems][System] within the region of a [`SpatialQuery`]
+    pub async fn systems_within(
+        &self,
+        query: &SpatialQuery,
+    ) -> Result<Vec<System>> {
+        self.send(endpoint::systems_within(query)?).await
+    }
+
+    /// Request the [Systems][System] within a sphere around a [`Center`]
+    ///
+    /// This is shorthand for [`AsyncClient::systems_within`] with a [`Shape::Sphere`].
+    pub async fn systems_sphere<C: Into<Center>>(
+        &self,
+        center: C,
+        radius: Option<f64>,
+        min_radius: Option<f64>,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let shape = Shape::Sphere { radius, min_radius };
+        self.systems_within(&SpatialQuery::new(center, shape).show(show))
+            .await
+    }
+
+    /// Request the [Systems][System] within a cube around a [`Center`]
+    ///
+    /// This is shorthand for [`AsyncClient::systems_within`] with a [`Shape::Cube`].
+    pub async fn systems_cube<C: Into<Center>>(
+        &self,
+        center: C,
+        size: Option<f64>,
+        show: Show,
+    ) -> Result<Vec<System>> {
+        let shape = Shape::Cube { size };
+        self.systems_within(&SpatialQuery::new(center, shape).show(show))
+            .await
+    }
+
+    /// Request a single [System]
+    pub async fn system(&self, name: &str, show: Show) -> Result<System> {
+        self.send(endpoint::system(name, &show)?).await
+    }
+
+    /// Request a single [System] along with the chosen [`Facets`], merged together
+    ///
+    /// Facets EDSM returns for a different system, like after a rename, are left out.
+    pub async fn system_with(
+        &self,
+        name: &str,
+        show: Show,
+        facets: Facets,
+    ) -> Result<System> {
+        let mut system = self.system(name, show).await?;
+        // Each facet is fetched after the system, so it's at least as new and its fields
+        // are meant to replace the system's.
+        for request in endpoint::facets(name, &facets)? {
+            let part = self.send(request).await?;
+            system.merge(part);
+        }
+        Ok(system)
+    }
+
+    /// Request a single [System]'s estimated exploration value
+    ///
+    /// The valuable bodies can be matched up with the system's [bodies][Self::bodies] by id.
+    pub async fn estimated_value(
+        &self,
+        system_name: &str,
+    ) -> Result<EstimatedValue> {
+        self.send(endpoint::estimated_value(system_name)?).await
+    }
+
+    /// Request a single [System]'s traffic report
+    pub async fn traffic(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::traffic(system_name)?).await
+    }
+
+    /// Request a single [System]'s death report
+    pub async fn deaths(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::deaths(system_name)?).await
+    }
+
+    /// Request a single [System] populated with many [Bodies][crate::Body]
+    pub async fn bodies(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::bodies(system_name)?).await
+    }
+
+    /// Request a single [System] populated with many [Stations][crate::Station]
+    pub async fn stations(&self, system_name: &str) -> Result<System> {
+        self.send(endpoint::stations(system_name)?).await
+    }
+
+    /// Request a station's [Market]
+    pub async fn market<S: Into<StationRef>>(
+        &self,
+        station: S,
+    ) -> Result<Market> {
+        self.send(endpoint::station_service("market", &station.into())?)
+            .await
+    }
+
+    /// Request a station's [Shipyard]
+    pub async fn shipyard<S: Into<StationRef>>(
+        &self,
+        station: S,
+    ) -> Result<Shipyard> {
+        self.send(endpoint::station_service("shipyard", &station.into())?)
+            .await
+    }
+
+    /// Request a station's [Outfitting]
+    pub async fn outfitting<S: Into<StationRef>>(
+        &self,
+        station: S,
+    ) -> Result<Outfitting> {
+        self.send(endpoint::station_service("outfitting", &station.into())?)
+            .await
+    }
+
+    /// Fetch a system with it's factions
+    ///
+    /// Passing a value of `true` for `history` will populate the appropriate history structures
+    /// within each faction.
+    pub async fn factions(
+        &self,
+        system_name: &str,
+        history: bool,
+    ) -> Result<System> {
+        self.send(endpoint::factions(system_name, history)?).await
+    }
+
+    /// Request the commander's [Ranks]
+    pub async fn ranks(&self) -> Result<Ranks> {
+        self.send(endpoint::ranks(self.credentials()?)?).await
+    }
+
+    /// Request the commander's current credit balance
+    pub async fn credits(&self) -> Result<Vec<Credits>> {
+        let reply: CreditsReply =
+            self.send(endpoint::credits(self.credentials()?)?).await?;
+        Ok(reply.credits)
+    }
+
+    /// Request the commander's materials, data or cargo
+    pub async fn inventory(
+        &self,
+        inventory: Inventory,
+    ) -> Result<Vec<InventoryItem>> {
+        let request = endpoint::inventory(self.credentials()?, inventory)?;
+        let reply: InventoryReply = self.send(request).await?;
+        Ok(reply.materials)
+    }
+
+    /// Request the commander's last known [Position]
+    pub async fn position(&self) -> Result<Position> {
+        self.send(endpoint::position(self.credentials()?)?).await
+    }
+
+    /// Request the [System] the commander was last seen in
+    ///
+    /// This is [`PositionNotPublic`][Error::PositionNotPublic] when the commander hides it.
+    pub async fn commander_system(&self, show: Show) -> Result<System> {
+        match self.position().await?.system {
+            Some(name) => self.system(&name, show).await,
+            None => Err(Error::PositionNotPublic(
+                self.credentials()?.commander_name().into(),
+            )),
+        }
+    }
+
+    /// Request the commander's flight log
+    ///
+    /// Without a start and end EDSM returns the last week, and longer ranges are cut down to a
+    /// week.
+    pub async fn flight_log(
+        &self,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Result<Vec<FlightLogEntry>> {
+        let request = endpoint::flight_log(self.credentials()?, start, end)?;
+        let reply: FlightLogReply = self.send(request).await?;
+        Ok(reply.logs)
+    }
+
+    /// Request the names of the journal events EDSM doesn't want uploaded
+    pub async fn discarded_events(&self) -> Result<DiscardList> {
+        self.send(endpoint::discard()?).await
+    }
+
+    /// Upload a [Journal]'s pending events, in batches
+    ///
+    /// The results are in the same order as the events. Events EDSM responded to are removed
+    /// from the journal even if they failed, so on an error only the remaining events are
+    /// pending, and the results of those already uploaded are part of the [`UploadError`].
+    pub async fn upload_journal(
+        &self,
+        journal: &mut Journal,
+    ) -> std::result::Result<Vec<EventResult>, UploadError> {
+        let credentials = self.credentials().map_err(|error| UploadError {
+            results: Vec::new(),
+            error,
+        })?;
+        let mut results = Vec::new();
+        while let Some(batch) = journal.batch() {
+            let count = batch.len();
+            let reply: Result<JournalReply> =
+                match endpoint::journal(credentials, journal) {
+                    Ok(request) => self.send(request).await,
+                    Err(e) => Err(e),
+                };
+            match reply {
+                Ok(reply) => {
+                    journal.sent(count);
+                    results.extend(reply.events);
+                }
+                Err(error) => return Err(UploadError { results, error }),
+            }
+        }
+        Ok(results)
+    }
+
+    fn credentials(&self) -> Result<&Credentials> {
+        self.credentials.as_deref().ok_or(Error::Unauthenticated)
+    }
+
+    // Get the JSON resource for the given request
+    async fn send<D: DeserializeOwned>(&self, request: Request) -> Result<D> {
+        let mut exchange = Exchange::new(
+            request,
+            &self.base_url,
+            &self.limiter,
+            &self.retry,
+            self.cache.as_ref(),
+        );
+        if let Some(cached) = exchange.cached_async().await? {
+            return Ok(cached);
+        }
+        loop {
+            let wait = exchange.throttle();
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+
+            let url = exchange.url().clone();
+            let http = match exchange.form() {
+                Some(form) => self
+                    .http
+                    .post(url)
+                    .header(CONTENT_TYPE, FORM_CONTENT_TYPE)
+                    .body(form.to_owned()),
+                None => self.http.get(url),
+            };
+            let delay = match http.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    match exchange.response(status, response.headers())? {
+                        Some(delay) => delay,
+                        // The connection can still drop while reading the body.
+                        None => match response.text().await {
+                            Ok(body) => {
+                                return exchange.finish_async(&body).await
+                            }
+                            Err(e) => exchange.error(e)?,
+                        },
+                    }
+                }
+                Err(e) => exchange.error(e)?,
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl Default for AsyncClient {
+    fn default() -> AsyncClient {
+        AsyncClient::new()
+    }
+}
@@ -0,0 +1,467 @@
+use super::endpoint::Request;
+use super::{
+    Error, Result, JOURNAL_PATH, STATUS_PATH, SYSTEMS_PATH, SYSTEM_PATH,
+};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A response body stored in a [`Cache`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub body: String,
+    pub stored: SystemTime,
+}
+
+/// Storage for EDSM's responses, keyed by request URL
+///
+/// Whether an entry is still fresh is decided by the client's [`CachePolicy`], so stores only
+/// need to keep what they're given. Responses to authenticated requests are never cached.
+///
+/// Stores may block, an [`AsyncClient`][super::AsyncClient] calls them on tokio's blocking
+/// thread pool.
+pub trait Cache: fmt::Debug + Send + Sync {
+    fn get(&self, key: &str) -> Option<Entry>;
+    fn put(&self, key: &str, entry: Entry);
+
+    /// Forget an entry which can't be used, by default it's only replaced once fetched again
+    fn remove(&self, _key: &str) {}
+}
+
+/// An in-memory [`Cache`] which evicts the least recently used entry when full
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    // Each entry, with the tick it was last used at.
+    entries: HashMap<String, (Entry, u64)>,
+    tick: u64,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> MemoryCache {
+        MemoryCache {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<Entry> {
+        let mut lru = self.lru.lock().expect("cache lock");
+        lru.tick += 1;
+        let tick = lru.tick;
+        let (entry, used) = lru.entries.get_mut(key)?;
+        *used = tick;
+        Some(entry.clone())
+    }
+
+    fn put(&self, key: &str, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lru = self.lru.lock().expect("cache lock");
+        lru.tick += 1;
+        let tick = lru.tick;
+        let entries = &mut lru.entries;
+        if !entries.contains_key(key) && entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.into(), (entry, tick));
+    }
+
+    fn remove(&self, key: &str) {
+        self.lru.lock().expect("cache lock").entries.remove(key);
+    }
+}
+
+/// A [`Cache`] keeping one file per response in a directory, so it lasts between runs
+///
+/// Failing to read or write a file is treated as a cache miss.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Use the given directory, creating it if needed
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<DiskCache> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir })
+    }
+
+    // The file for a key, named after its hash.
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", key_hash(key)))
+    }
+}
+
+// FNV-1a, so file names derived from a key are stable between builds.
+pub(crate) fn key_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Cache for DiskCache {
+    // Each file is the time stored, the key, and then the body, on their own lines.
+    fn get(&self, key: &str) -> Option<Entry> {
+        let contents = fs::read_to_string(self.path(key)).ok()?;
+        let mut parts = contents.splitn(3, '\n');
+        let secs = parts.next()?.parse().ok()?;
+        if parts.next()? != key {
+            return None;
+        }
+        Some(Entry {
+            body: parts.next()?.into(),
+            stored: UNIX_EPOCH + Duration::from_secs(secs),
+        })
+    }
+
+    fn put(&self, key: &str, entry: Entry) {
+        let secs = entry
+            .stored
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let contents = format!("{}\n{}\n{}", secs, key, entry.body);
+        // Write then rename, so readers never see part of a file. Each write has its own
+        // temporary file, so concurrent writers of a key can't interleave.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let path = self.path(key);
+        let tmp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written =
+            fs::write(&tmp, contents).and_then(|_| fs::rename(&tmp, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+}
+
+/// How long cached responses stay fresh, by API path
+///
+/// The defaults follow how often EDSM's data changes: bodies rarely do, while factions, traffic
+/// and markets change daily or faster. A zero TTL turns caching off for that path.
+///
+/// ```
+/// use edsm::api::CachePolicy;
+/// use std::time::Duration;
+///
+/// let policy = CachePolicy::default()
+///     .ttl("api-system-v1/factions", Duration::from_secs(15 * 60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    default_ttl: Duration,
+    ttls: HashMap<String, Duration>,
+}
+
+impl CachePolicy {
+    /// Use the same TTL for every path
+    pub fn new(default_ttl: Duration) -> CachePolicy {
+        CachePolicy {
+            default_ttl,
+            ttls: HashMap::new(),
+        }
+    }
+
+    /// Use a different TTL for the given path, like `api-system-v1/bodies`
+    pub fn ttl<P: Into<String>>(
+        mut self,
+        path: P,
+        ttl: Duration,
+    ) -> CachePolicy {
+        self.ttls.insert(path.into(), ttl);
+        self
+    }
+
+    /// How long responses from the given path stay fresh
+    pub fn ttl_for(&self, path: &str) -> Duration {
+        self.ttls.get(path).copied().unwrap_or(self.default_ttl)
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> CachePolicy {
+        let path = |api, endpoint| format!("{}/{}", api, endpoint);
+        CachePolicy::new(HOUR)
+            .ttl(path(SYSTEMS_PATH, "system"), DAY)
+            .ttl(path(SYSTEMS_PATH, "systems"), DAY)
+            .ttl(path(SYSTEMS_PATH, "sphere-systems"), DAY)
+            .ttl(path(SYSTEMS_PATH, "cube-systems"), DAY)
+            .ttl(path(SYSTEM_PATH, "bodies"), 7 * DAY)
+            .ttl(path(SYSTEM_PATH, "estimated-value"), 7 * DAY)
+            .ttl(path(SYSTEM_PATH, "stations"), DAY)
+            .ttl(path(STATUS_PATH, "elite-server"), MINUTE)
+            .ttl(path(JOURNAL_PATH, "discard"), DAY)
+    }
+}
+
+// A client's cache, along with how to use it.
+#[derive(Debug, Clone)]
+pub(crate) struct Layer {
+    store: Arc<dyn Cache>,
+    policy: Arc<CachePolicy>,
+    offline: bool,
+}
+
+impl Layer {
+    pub fn new(
+        store: Arc<dyn Cache>,
+        policy: CachePolicy,
+        offline: bool,
+    ) -> Layer {
+        Layer {
+            store,
+            policy: Arc::new(policy),
+            offline,
+        }
+    }
+
+    /// The body to use instead of sending the request, if it's cached and fresh
+    ///
+    /// Offline, stale entries are used too, and anything else is an error.
+    pub fn get(&self, request: &Request, url: &Url) -> Result<Option<String>> {
+        let entry = if request.is_cacheable() {
+            self.store.get(url.as_str())
+        } else {
+            None
+        };
+        self.usable(request, url, entry)
+    }
+
+    /// [`get`][Self::get], reading the store without blocking the async runtime
+    #[cfg(feature = "async")]
+    pub async fn get_async(
+        &self,
+        request: &Request,
+        url: &Url,
+    ) -> Result<Option<String>> {
+        let entry = if request.is_cacheable() {
+            let store = Arc::clone(&self.store);
+            let key = url.to_string();
+            blocking(move || store.get(&key)).await
+        } else {
+            None
+        };
+        self.usable(request, url, entry)
+    }
+
+    pub fn put(&self, request: &Request, url: &Url, body: &str) {
+        if let Some(entry) = self.entry(request, body) {
+            self.store.put(url.as_str(), entry);
+        }
+    }
+
+    /// [`put`][Self::put], writing the store without blocking the async runtime
+    #[cfg(feature = "async")]
+    pub async fn put_async(&self, request: &Request, url: &Url, body: &str) {
+        if let Some(entry) = self.entry(request, body) {
+            let store = Arc::clone(&self.store);
+            let key = url.to_string();
+            blocking(move || store.put(&key, entry)).await;
+        }
+    }
+
+    /// Whether stale entries are used, and missing ones are errors
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Forget the entry for a request, after its body failed to decode
+    pub fn remove(&self, url: &Url) {
+        self.store.remove(url.as_str());
+    }
+
+    /// [`remove`][Self::remove], without blocking the async runtime
+    #[cfg(feature = "async")]
+    pub async fn remove_async(&self, url: &Url) {
+        let store = Arc::clone(&self.store);
+        let key = url.to_string();
+        blocking(move || store.remove(&key)).await;
+    }
+
+    // Whether the stored entry for a request, if any, can be used.
+    fn usable(
+        &self,
+        request: &Request,
+        url: &Url,
+        entry: Option<Entry>,
+    ) -> Result<Option<String>> {
+        if !request.is_cacheable() {
+            return if self.offline {
+                Err(Error::NotCached(url.to_string()))
+            } else {
+                Ok(None)
+            };
+        }
+
+        let ttl = self.policy.ttl_for(&request.path);
+        match entry {
+            Some(entry) if self.offline => Ok(Some(entry.body)),
+            Some(entry) if is_fresh(&entry, ttl) => Ok(Some(entry.body)),
+            _ if self.offline => Err(Error::NotCached(url.to_string())),
+            _ => Ok(None),
+        }
+    }
+
+    // The entry to store for a response, unless it shouldn't be cached.
+    fn entry(&self, request: &Request, body: &str) -> Option<Entry> {
+        let ttl = self.policy.ttl_for(&request.path);
+        if request.is_cacheable() && !ttl.is_zero() {
+            Some(Entry {
+                body: body.into(),
+                stored: SystemTime::now(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// Run a store's (possibly blocking) method on tokio's blocking thread pool.
+#[cfg(feature = "async")]
+async fn blocking<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        // Panic as a blocking client would, within the caller's task.
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+fn is_fresh(entry: &Entry, ttl: Duration) -> bool {
+    match entry.stored.elapsed() {
+        Ok(age) => age < ttl,
+        // Stored in the future, the clock must have changed.
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(body: &str) -> Entry {
+        Entry {
+            body: body.into(),
+            stored: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+        }
+    }
+
+    #[test]
+    fn memory_cache() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", entry("1"));
+        cache.put("b", entry("2"));
+        assert!(cache.get("a").is_some());
+        cache.put("c", entry("3"));
+        assert!(cache.get("b").is_none());
+        assert_eq!(Some(entry("1")), cache.get("a"));
+        assert_eq!(Some(entry("3")), cache.get("c"));
+        cache.remove("c");
+        assert!(cache.get("c").is_none());
+    }
+
+    #[test]
+    fn disk_cache() {
+        let dir = std::env::temp_dir()
+            .join(format!("edsm-disk-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir).unwrap();
+        assert!(cache.get("https://www.edsm.net/a").is_none());
+        cache.put("https://www.edsm.net/a", entry("{\n\"name\": \"Sol\"\n}"));
+        assert_eq!(
+            Some(entry("{\n\"name\": \"Sol\"\n}")),
+            cache.get("https://www.edsm.net/a")
+        );
+
+        // Concurrent writers of a key leave one of their bodies whole.
+        let cache = Arc::new(cache);
+        let bodies: Vec<_> =
+            (0..8).map(|i| i.to_string().repeat(1 << 16)).collect();
+        let writers: Vec<_> = bodies
+            .iter()
+            .map(|body| {
+                let (cache, body) = (Arc::clone(&cache), body.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        cache.put("https://www.edsm.net/b", entry(&body));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let stored = cache.get("https://www.edsm.net/b").unwrap();
+        assert!(bodies.contains(&stored.body));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn policy() {
+        let policy = CachePolicy::default().ttl("api-v1/system", MINUTE);
+        assert_eq!(7 * DAY, policy.ttl_for("api-system-v1/bodies"));
+        assert_eq!(MINUTE, policy.ttl_for("api-v1/system"));
+        assert_eq!(HOUR, policy.ttl_for("api-system-v1/factions"));
+
+        let fresh = Entry {
+            body: "{}".into(),
+            stored: SystemTime::now(),
+        };
+        assert!(is_fresh(&fresh, MINUTE));
+        assert!(!is_fresh(&entry("{}"), DAY));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_layer() {
+        let layer = Layer::new(
+            Arc::new(MemoryCache::new(2)),
+            Default::default(),
+            false,
+        );
+        let request = super::super::endpoint::bodies("Sol").unwrap();
+        let url =
+            Url::parse("https://www.edsm.net/api-system-v1/bodies").unwrap();
+        assert_eq!(None, layer.get_async(&request, &url).await.unwrap());
+        layer.put_async(&request, &url, "{}").await;
+        assert_eq!(Some("{}".into()), layer.get(&request, &url).unwrap());
+        assert_eq!(
+            Some("{}".into()),
+            layer.get_async(&request, &url).await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+/// A commander's name and EDSM API key, needed for the commander and flight log APIs
+///
+/// The key is found under the API key section of EDSM's settings. It's never shown by `Debug`,
+/// and is sent in the body of a `POST` request, so it stays out of URLs and the logs that
+/// record them.
+///
+/// ```no_run
+/// use edsm::api::{Client, Credentials};
+///
+/// let credentials = Credentials::new("Jameson", std::env::var("EDSM_API_KEY").unwrap());
+/// let client = Client::builder().credentials(credentials).build().unwrap();
+/// let ranks = client.ranks().unwrap();
+/// ```
+pub struct Credentials {
+    commander_name: String,
+    api_key: String,
+}
+
+impl Credentials {
+    pub fn new<N, K>(commander_name: N, api_key: K) -> Credentials
+    where
+        N: Into<String>,
+        K: Into<String>,
+    {
+        Credentials {
+            commander_name: commander_name.into(),
+            api_key: api_key.into(),
+        }
+    }
+
+    pub fn commander_name(&self) -> &str {
+        &self.commander_name
+    }
+
+    pub(crate) fn api_key(&self) -> &str {
+        &self.api_key
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("commander_name", &self.commander_name)
+            .field("api_key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+/// Which of a commander's holds to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inventory {
+    /// Raw, manufactured and encoded materials
+    Materials,
+    /// Odyssey data
+    Data,
+    /// The ship's cargo hold
+    Cargo,
+}
+
+impl Inventory {
+    // The value of EDSM's `type` parameter
+    pub(crate) fn param(self) -> &'static str {
+        match self {
+            Inventory::Materials => "materials",
+            Inventory::Data => "data",
+            Inventory::Cargo => "cargo",
+        }
+    }
+}
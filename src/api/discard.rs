@@ -0,0 +1,276 @@
+use elite_journal::entry::{Entry, Event};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::hash_set::{self, HashSet};
+use std::iter::FromIterator;
+
+// A snapshot of EDSM's discard list, for when it can't be fetched.
+const BUNDLED: &[&str] = &[
+    "ApproachBody",
+    "ApproachSettlement",
+    "Backpack",
+    "BackpackChange",
+    "CargoTransfer",
+    "CockpitBreached",
+    "CommitCrime",
+    "CrewLaunchFighter",
+    "CrewMemberJoins",
+    "CrewMemberQuits",
+    "CrewMemberRoleChange",
+    "DataScanned",
+    "DockingCancelled",
+    "DockingDenied",
+    "DockingGranted",
+    "DockingRequested",
+    "DockingTimeout",
+    "EjectCargo",
+    "EscapeInterdiction",
+    "FSDTarget",
+    "FighterDestroyed",
+    "FighterRebuilt",
+    "Friends",
+    "FuelScoop",
+    "HeatDamage",
+    "HeatWarning",
+    "HullDamage",
+    "Interdicted",
+    "Interdiction",
+    "LaunchDrone",
+    "LaunchFighter",
+    "LeaveBody",
+    "Liftoff",
+    "Music",
+    "NavRoute",
+    "NavRouteClear",
+    "NpcCrewPaidWage",
+    "NpcCrewRank",
+    "ReceiveText",
+    "ReservoirReplenished",
+    "Screenshot",
+    "SendText",
+    "ShieldState",
+    "ShipTargeted",
+    "Shutdown",
+    "SquadronStartup",
+    "StartJump",
+    "SupercruiseEntry",
+    "SupercruiseExit",
+    "SystemsShutdown",
+    "Touchdown",
+    "UnderAttack",
+    "VehicleSwitch",
+    "WingAdd",
+    "WingInvite",
+    "WingJoin",
+    "WingLeave",
+];
+
+/// The journal events EDSM asks not to be sent
+///
+/// Fetch the current list with [`Client::discarded_events`][super::Client::discarded_events], or
+/// use the [bundled][DiscardList::bundled] copy when offline.
+///
+/// ```
+/// use edsm::api::DiscardList;
+///
+/// let discard = DiscardList::bundled();
+/// let lines = vec![
+///     r#"{ "timestamp": "2021-03-01T18:21:47Z", "event": "Music", "MusicTrack": "NoTrack" }"#,
+///     r#"{ "timestamp": "2021-03-01T18:21:48Z", "event": "Docked", "StationName": "Daedalus" }"#,
+/// ];
+/// let kept: Vec<_> = discard.filter(lines).collect();
+/// assert_eq!(1, kept.len());
+/// ```
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct DiscardList(HashSet<String>);
+
+impl DiscardList {
+    /// The list as it was when this version of the crate was released
+    ///
+    /// EDSM changes the list from time to time, so prefer fetching it when possible.
+    pub fn bundled() -> DiscardList {
+        BUNDLED.iter().copied().collect()
+    }
+
+    /// Whether events with this name are discarded
+    pub fn contains(&self, event_name: &str) -> bool {
+        self.0.contains(event_name)
+    }
+
+    /// Whether the given event should be discarded
+    ///
+    /// Events without a name aren't discarded, so EDSM can report them as invalid.
+    pub fn is_discarded<E: EventName + ?Sized>(&self, event: &E) -> bool {
+        event.event_name().is_some_and(|name| self.contains(name))
+    }
+
+    /// Keep only the events which shouldn't be discarded
+    pub fn filter<'a, I>(
+        &'a self,
+        events: I,
+    ) -> impl Iterator<Item = I::Item> + 'a
+    where
+        I: IntoIterator + 'a,
+        I::Item: EventName,
+    {
+        events.into_iter().filter(move |e| !self.is_discarded(e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> hash_set::Iter<'_, String> {
+        self.0.iter()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for DiscardList {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> DiscardList {
+        DiscardList(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<S: Into<String>> Extend<S> for DiscardList {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(Into::into))
+    }
+}
+
+/// A journal event with a name, which a [`DiscardList`] filters by
+///
+/// This is implemented for raw journal lines, parsed JSON, and `elite_journal`'s typed events.
+pub trait EventName {
+    /// The value of the event's `event` field
+    fn event_name(&self) -> Option<&str>;
+}
+
+impl EventName for Map<String, Value> {
+    fn event_name(&self) -> Option<&str> {
+        self.get("event").and_then(Value::as_str)
+    }
+}
+
+impl EventName for Value {
+    fn event_name(&self) -> Option<&str> {
+        self.get("event").and_then(Value::as_str)
+    }
+}
+
+// A journal line, only the event name is parsed.
+impl EventName for str {
+    fn event_name(&self) -> Option<&str> {
+        #[derive(Deserialize)]
+        struct Named<'a> {
+            event: &'a str,
+        }
+
+        serde_json::from_str::<Named>(self).ok().map(|n| n.event)
+    }
+}
+
+impl EventName for String {
+    fn event_name(&self) -> Option<&str> {
+        self.as_str().event_name()
+    }
+}
+
+// Events `elite_journal` doesn't know lose their name, so they're never discarded.
+impl EventName for Event {
+    fn event_name(&self) -> Option<&str> {
+        let name = match self {
+            Event::Fileheader(_) => "Fileheader",
+            Event::Cargo(_) => "Cargo",
+            Event::NewCommander(_) => "NewCommander",
+            Event::ClearSavedGame(_) => "ClearSavedGame",
+            Event::Commander(_) => "Commander",
+            Event::LoadGame(_) => "LoadGame",
+            Event::Materials(_) => "Materials",
+            Event::Location(_) => "Location",
+            Event::Docked(_) => "Docked",
+            Event::DockingGranted(_) => "DockingGranted",
+            Event::DockingRequested(_) => "DockingRequested",
+            Event::DockingDenied(_) => "DockingDenied",
+            Event::DockingCancelled(_) => "DockingCancelled",
+            Event::DockingTimeout(_) => "DockingTimeout",
+            Event::Undocked(_) => "Undocked",
+            Event::Liftoff(_) => "Liftoff",
+            Event::LeaveBody(_) => "LeaveBody",
+            Event::ApproachBody(_) => "ApproachBody",
+            Event::ApproachSettlement(_) => "ApproachSettlement",
+            Event::FsdTarget(_) => "FSDTarget",
+            Event::FsdJump(_) => "FSDJump",
+            Event::CarrierJump(_) => "CarrierJump",
+            Event::NavRoute(_) => "NavRoute",
+            Event::BuyExplorationData(_) => "BuyExplorationData",
+            Event::SellExplorationData(_) => "SellExplorationData",
+            Event::SAASignalsFound(_) => "SAASignalsFound",
+            Event::Scan(_) => "Scan",
+            Event::ScanBaryCentre(_) => "ScanBaryCentre",
+            Event::FssDiscoveryScan(_) => "FSSDiscoveryScan",
+            Event::FssAllBodiesFound(_) => "FSSAllBodiesFound",
+            Event::NavBeaconScan(_) => "NavBeaconScan",
+            Event::FssBodySignals(_) => "FSSBodySignals",
+            Event::FssSignalDiscovered(_) => "FSSSignalDiscovered",
+            Event::CodexEntry(_) => "CodexEntry",
+            Event::Other => return None,
+        };
+        Some(name)
+    }
+}
+
+impl<E: EventName> EventName for Entry<E> {
+    fn event_name(&self) -> Option<&str> {
+        self.event.event_name()
+    }
+}
+
+impl<E: EventName + ?Sized> EventName for &E {
+    fn event_name(&self) -> Option<&str> {
+        (**self).event_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn discard_list() {
+        let discard: DiscardList =
+            serde_json::from_str(r#"["Music", "Friends"]"#).unwrap();
+        assert_eq!(2, discard.len());
+        assert!(discard.is_discarded(&json!({ "event": "Music" })));
+        assert!(!discard.is_discarded(&json!({ "event": "FSDJump" })));
+        assert!(!discard.is_discarded(&json!({})));
+        assert!(
+            discard.is_discarded(r#"{"event":"Friends","Status":"Online"}"#)
+        );
+        assert!(!discard.is_discarded("not json"));
+        assert!(DiscardList::bundled().contains("Music"));
+    }
+
+    #[test]
+    fn typed_events() {
+        let discard = DiscardList::bundled();
+        let granted: Entry<Event> = serde_json::from_str(
+            r#"{ "timestamp": "2021-03-01T18:21:47Z", "event": "DockingGranted",
+                "StationName": "Daedalus", "MarketID": 128106744, "LandingPad": 4 }"#,
+        )
+        .unwrap();
+        assert_eq!(Some("DockingGranted"), granted.event_name());
+        assert!(discard.is_discarded(&granted));
+        let music: Entry<Event> = serde_json::from_str(
+            r#"{ "timestamp": "2021-03-01T18:21:48Z", "event": "Music", "MusicTrack": "NoTrack" }"#,
+        )
+        .unwrap();
+        assert_eq!(None, music.event_name());
+        assert!(!discard.is_discarded(&music));
+    }
+}
@@ -0,0 +1,632 @@
+use super::cache::Layer;
+use super::rate_limit::Limiter;
+use super::{
+    check_status, decode, Center, Coordinates, Credentials, Error, Facets,
+    Inventory, Journal, Result, RetryPolicy, Shape, Show, SpatialQuery,
+    StationRef, SystemsQuery, COMMANDER_PATH, DEFAULT_SPHERE_RADIUS,
+    JOURNAL_PATH, LOGS_PATH, MAX_CUBE_SIZE, MAX_SPHERE_RADIUS, STATUS_PATH,
+    SYSTEMS_PATH, SYSTEM_PATH,
+};
+use chrono::NaiveDateTime;
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+// The format of EDSM's date parameters, which are always in UTC.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A single API call, relative to a client's base URL
+///
+/// Building requests separately from sending them lets the blocking and async clients share
+/// everything but the transport.
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub path: String,
+    pub params: Vec<(&'static str, String)>,
+    // What this request is about, for reporting it missing.
+    subject: Option<Subject>,
+    // Whether the params are sent as a form, keeping secrets like API keys out of the URL.
+    post: bool,
+    // Whether the body reports success with EDSM's `msgnum` field.
+    msgnum: bool,
+    // Whether sending this twice is harmless, which isn't so for uploads.
+    idempotent: bool,
+}
+
+impl Request {
+    fn new(api: &str, endpoint: &str) -> Request {
+        let path = if endpoint.is_empty() {
+            api.into()
+        } else {
+            format!("{}/{}", api, endpoint)
+        };
+        Request {
+            path,
+            params: Vec::new(),
+            subject: None,
+            post: false,
+            msgnum: false,
+            idempotent: true,
+        }
+    }
+
+    // A request for a commander's private data, which EDSM reports errors for in the body.
+    fn authenticated(
+        api: &str,
+        endpoint: &str,
+        credentials: &Credentials,
+    ) -> Result<Request> {
+        let commander = name("commanderName", credentials.commander_name())?;
+        let mut request = Request::new(api, endpoint)
+            .param("commanderName", commander)
+            .param("apiKey", name("apiKey", credentials.api_key())?);
+        request.post = true;
+        request.msgnum = true;
+        Ok(request)
+    }
+
+    // Mark this as a request for the named system's record.
+    fn about(mut self, name: &str) -> Request {
+        self.subject = Some(Subject::System {
+            name: name.into(),
+            single: true,
+        });
+        self
+    }
+
+    // Mark this as a request for many systems around the named system.
+    fn around(mut self, name: &str) -> Request {
+        self.subject = Some(Subject::System {
+            name: name.into(),
+            single: false,
+        });
+        self
+    }
+
+    // Mark this as a request for one of a station's services.
+    fn at(mut self, station: &StationRef) -> Request {
+        self.subject = Some(Subject::Station(station.clone()));
+        self
+    }
+
+    /// The error for a missing system or station, if the given response body means it wasn't
+    /// found
+    ///
+    /// EDSM responds to unknown systems with `{}`, or `[]` for endpoints returning a single
+    /// record. An empty array is a valid result when searching around a system.
+    pub fn not_found(&self, body: &str) -> Option<Error> {
+        match &self.subject {
+            Some(Subject::System { name, single })
+                if body == "{}" || (*single && body == "[]") =>
+            {
+                Some(Error::NotFound(name.clone()))
+            }
+            Some(Subject::Station(station)) if body == "{}" || body == "[]" => {
+                Some(Error::StationNotFound(station.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the response may be cached, which private data mustn't be
+    pub fn is_cacheable(&self) -> bool {
+        !self.post
+    }
+
+    /// Whether the request may be retried after EDSM could have acted on it
+    pub fn is_idempotent(&self) -> bool {
+        self.idempotent
+    }
+
+    /// Whether the response body carries a `msgnum` which must be checked
+    pub fn has_msgnum(&self) -> bool {
+        self.msgnum
+    }
+
+    fn param<V: ToString>(mut self, key: &'static str, value: V) -> Request {
+        self.params.push((key, value.to_string()));
+        self
+    }
+
+    fn show(mut self, show: &Show) -> Request {
+        for key in show.params() {
+            self = self.param(key, 1);
+        }
+        self
+    }
+
+    /// The full URL of this request under the given base URL
+    ///
+    /// The base URL must be able to be a base, which the client builder checks. The params are
+    /// only part of the URL when this request isn't sent as a [form][Self::form].
+    pub fn url(&self, base: &Url) -> Url {
+        let mut url = base.clone();
+        url.path_segments_mut()
+            .expect("base URL checked by the builder")
+            .pop_if_empty()
+            .extend(self.path.split('/'));
+        if !self.post {
+            url.query_pairs_mut().extend_pairs(&self.params);
+        }
+        url
+    }
+
+    /// The URL encoded body to `POST`, or `None` to `GET` the URL instead
+    pub fn form(&self) -> Option<String> {
+        if self.post {
+            let mut form = form_urlencoded::Serializer::new(String::new());
+            Some(form.extend_pairs(&self.params).finish())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Subject {
+    // A system's record, or many systems around it when not `single`.
+    System { name: String, single: bool },
+    Station(StationRef),
+}
+
+/// A [Request] being sent, deciding what happens after each attempt
+///
+/// The blocking and async clients only differ in how they send HTTP requests and wait, so
+/// throttling, retries, caching and decoding are all handled here.
+pub(crate) struct Exchange<'a> {
+    request: Request,
+    url: Url,
+    form: Option<String>,
+    limiter: &'a Limiter,
+    retry: &'a RetryPolicy,
+    cache: Option<&'a Layer>,
+    attempt: u32,
+}
+
+impl<'a> Exchange<'a> {
+    pub fn new(
+        request: Request,
+        base_url: &Url,
+        limiter: &'a Limiter,
+        retry: &'a RetryPolicy,
+        cache: Option<&'a Layer>,
+    ) -> Exchange<'a> {
+        Exchange {
+            url: request.url(base_url),
+            form: request.form(),
+            request,
+            limiter,
+            retry,
+            cache,
+            attempt: 1,
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The URL encoded body to `POST`, or `None` to `GET` the URL instead
+    pub fn form(&self) -> Option<&str> {
+        self.form.as_deref()
+    }
+
+    /// The cached response, when the request doesn't need sending at all
+    ///
+    /// Only bodies which decoded are cached, so one which no longer does, like one written by an
+    /// older version of this crate, is forgotten and fetched again. Offline it's an error.
+    pub fn cached<D: DeserializeOwned>(&self) -> Result<Option<D>> {
+        let cache = match self.cache {
+            Some(cache) => cache,
+            None => return Ok(None),
+        };
+        let body = match cache.get(&self.request, &self.url)? {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        match decode(&self.request, &body) {
+            Ok(decoded) => Ok(Some(decoded)),
+            Err(error) if cache.is_offline() => Err(error),
+            Err(_) => {
+                cache.remove(&self.url);
+                Ok(None)
+            }
+        }
+    }
+
+    /// [`cached`][Self::cached], without blocking the async runtime on the cache
+    #[cfg(feature = "async")]
+    pub async fn cached_async<D: DeserializeOwned>(&self) -> Result<Option<D>> {
+        let cache = match self.cache {
+            Some(cache) => cache,
+            None => return Ok(None),
+        };
+        let body = match cache.get_async(&self.request, &self.url).await? {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        match decode(&self.request, &body) {
+            Ok(decoded) => Ok(Some(decoded)),
+            Err(error) if cache.is_offline() => Err(error),
+            Err(_) => {
+                cache.remove_async(&self.url).await;
+                Ok(None)
+            }
+        }
+    }
+
+    /// How long to wait before the next attempt, to stay within the throttle
+    pub fn throttle(&self) -> Duration {
+        self.limiter.acquire()
+    }
+
+    /// How long to wait before retrying after the given response, or `None` to read its body
+    pub fn response(
+        &mut self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<Option<Duration>> {
+        self.limiter.observe(headers);
+        let idempotent = self.request.is_idempotent();
+        match self
+            .retry
+            .after_status(self.attempt, status, headers, idempotent)
+        {
+            Some(delay) => {
+                self.attempt += 1;
+                Ok(Some(delay))
+            }
+            None => check_status(status, headers).map(|_| None),
+        }
+    }
+
+    /// How long to wait before retrying after failing to get a response
+    pub fn error(&mut self, error: reqwest::Error) -> Result<Duration> {
+        let idempotent = self.request.is_idempotent();
+        match self.retry.after_error(self.attempt, &error, idempotent) {
+            Some(delay) => {
+                self.attempt += 1;
+                Ok(delay)
+            }
+            None => Err(error.into()),
+        }
+    }
+
+    /// Decode a successful response's body, caching it
+    pub fn finish<D: DeserializeOwned>(&self, body: &str) -> Result<D> {
+        let decoded = decode(&self.request, body)?;
+        if let Some(cache) = self.cache {
+            cache.put(&self.request, &self.url, body);
+        }
+        Ok(decoded)
+    }
+
+    /// [`finish`][Self::finish], without blocking the async runtime on the cache
+    #[cfg(feature = "async")]
+    pub async fn finish_async<D: DeserializeOwned>(
+        &self,
+        body: &str,
+    ) -> Result<D> {
+        let decoded = decode(&self.request, body)?;
+        if let Some(cache) = self.cache {
+            cache.put_async(&self.request, &self.url, body).await;
+        }
+        Ok(decoded)
+    }
+}
+
+// Check a name is usable before sending it to EDSM.
+fn name<'a>(key: &'static str, name: &'a str) -> Result<&'a str> {
+    if name.trim().is_empty() {
+        Err(Error::InvalidParameter {
+            name: key,
+            reason: "must not be empty".into(),
+        })
+    } else {
+        Ok(name)
+    }
+}
+
+// Check a distance is within the (inclusive) bounds EDSM accepts.
+fn distance(key: &'static str, value: f64, min: f64, max: f64) -> Result<f64> {
+    if value.is_finite() && value >= min && value <= max {
+        Ok(value)
+    } else {
+        Err(Error::InvalidParameter {
+            name: key,
+            reason: format!("{} is not between {} and {}", value, min, max),
+        })
+    }
+}
+
+// Add the parameters for searching around a center point.
+fn center(request: Request, center: &Center) -> Result<Request> {
+    match center {
+        Center::Name(system_name) => Ok(request
+            .param("systemName", name("systemName", system_name)?)
+            .around(system_name)),
+        Center::Coords(coords) => {
+            let axis = |key, value: f64| {
+                if value.is_finite() {
+                    Ok(value)
+                } else {
+                    Err(Error::InvalidParameter {
+                        name: key,
+                        reason: format!("{} is not a coordinate", value),
+                    })
+                }
+            };
+            Ok(request
+                .param("x", axis("x", coords.x)?)
+                .param("y", axis("y", coords.y)?)
+                .param("z", axis("z", coords.z)?))
+        }
+    }
+}
+
+pub(crate) fn systems(query: &SystemsQuery) -> Result<Request> {
+    let mut request = Request::new(SYSTEMS_PATH, "systems");
+    match query.names.as_slice() {
+        [] if query.start.is_none() && query.end.is_none() => {
+            return Err(Error::InvalidParameter {
+                name: "systemName",
+                reason: "a name or date range is required".into(),
+            });
+        }
+        [] => {}
+        // EDSM matches a single name by prefix, unless it's sent as a list.
+        [single] if !query.exact => {
+            request = request.param("systemName", name("systemName", single)?);
+        }
+        names => {
+            for n in names {
+                request = request.param("systemName[]", name("systemName", n)?);
+            }
+        }
+    }
+
+    if query.only_featured {
+        if query.show.information {
+            return Err(Error::InvalidParameter {
+                name: "onlyFeatured",
+                reason: "can't be combined with showInformation".into(),
+            });
+        }
+        request = request.param("onlyFeatured", 1);
+    }
+
+    match query.coordinates {
+        Some(Coordinates::Known) => {
+            request = request.param("onlyKnownCoordinates", 1)
+        }
+        Some(Coordinates::Unknown) => {
+            request = request.param("onlyUnknownCoordinates", 1)
+        }
+        None => {}
+    }
+
+    if let (Some(start), Some(end)) = (query.start, query.end) {
+        if start > end {
+            return Err(Error::InvalidParameter {
+                name: "startDateTime",
+                reason: "must not be after endDateTime".into(),
+            });
+        }
+    }
+    if let Some(start) = query.start {
+        request = request.param("startDateTime", start.format(DATE_FORMAT));
+    }
+    if let Some(end) = query.end {
+        request = request.param("endDateTime", end.format(DATE_FORMAT));
+    }
+
+    Ok(request.show(&query.show))
+}
+
+// TODO: What exactly is a cube's `size`?
+pub(crate) fn systems_within(query: &SpatialQuery) -> Result<Request> {
+    let request = match query.shape {
+        Shape::Sphere { radius, min_radius } => {
+            let mut request = center(
+                Request::new(SYSTEMS_PATH, "sphere-systems"),
+                &query.center,
+            )?;
+            if let Some(r) = radius {
+                let r = distance("radius", r, 0., MAX_SPHERE_RADIUS)?;
+                request = request.param("radius", r);
+            }
+            if let Some(m) = min_radius {
+                let max = radius.unwrap_or(DEFAULT_SPHERE_RADIUS);
+                let m = distance("minRadius", m, 0., max)?;
+                request = request.param("minRadius", m);
+            }
+            request
+        }
+        Shape::Cube { size } => {
+            let mut request = center(
+                Request::new(SYSTEMS_PATH, "cube-systems"),
+                &query.center,
+            )?;
+            if let Some(s) = size {
+                let s = distance("size", s, 0., MAX_CUBE_SIZE)?;
+                request = request.param("size", s);
+            }
+            request
+        }
+    };
+
+    Ok(request.show(&query.show))
+}
+
+pub(crate) fn system(system_name: &str, show: &Show) -> Result<Request> {
+    Ok(Request::new(SYSTEMS_PATH, "system")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name)
+        .show(show))
+}
+
+// A request for each of the chosen facets of a system.
+pub(crate) fn facets(
+    system_name: &str,
+    facets: &Facets,
+) -> Result<Vec<Request>> {
+    let mut requests = Vec::new();
+    if facets.bodies {
+        requests.push(bodies(system_name)?);
+    }
+    if facets.stations {
+        requests.push(stations(system_name)?);
+    }
+    if facets.factions {
+        requests.push(factions(system_name, facets.faction_history)?);
+    }
+    if facets.traffic {
+        requests.push(traffic(system_name)?);
+    }
+    if facets.deaths {
+        requests.push(deaths(system_name)?);
+    }
+    Ok(requests)
+}
+
+pub(crate) fn estimated_value(system_name: &str) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "estimated-value")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name))
+}
+
+pub(crate) fn traffic(system_name: &str) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "traffic")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name))
+}
+
+pub(crate) fn deaths(system_name: &str) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "deaths")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name))
+}
+
+pub(crate) fn bodies(system_name: &str) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "bodies")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name))
+}
+
+pub(crate) fn stations(system_name: &str) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "stations")
+        .param("systemName", name("systemName", system_name)?)
+        .about(system_name))
+}
+
+// A station's market, shipyard or outfitting.
+pub(crate) fn station_service(
+    service: &str,
+    station: &StationRef,
+) -> Result<Request> {
+    let request =
+        Request::new(SYSTEM_PATH, &format!("stations/{}", service)).at(station);
+    Ok(match station {
+        StationRef::MarketId(id) => request.param("marketId", id),
+        StationRef::Name {
+            system,
+            station: station_name,
+        } => request
+            .param("systemName", name("systemName", system)?)
+            .param("stationName", name("stationName", station_name)?),
+    })
+}
+
+pub(crate) fn factions(system_name: &str, history: bool) -> Result<Request> {
+    Ok(Request::new(SYSTEM_PATH, "factions")
+        .param("systemName", name("systemName", system_name)?)
+        .param("showHistory", history as u8)
+        .about(system_name))
+}
+
+pub(crate) fn ranks(credentials: &Credentials) -> Result<Request> {
+    Request::authenticated(COMMANDER_PATH, "get-ranks", credentials)
+}
+
+pub(crate) fn credits(credentials: &Credentials) -> Result<Request> {
+    Request::authenticated(COMMANDER_PATH, "get-credits", credentials)
+}
+
+pub(crate) fn inventory(
+    credentials: &Credentials,
+    inventory: Inventory,
+) -> Result<Request> {
+    Ok(
+        Request::authenticated(COMMANDER_PATH, "get-materials", credentials)?
+            .param("type", inventory.param()),
+    )
+}
+
+pub(crate) fn position(credentials: &Credentials) -> Result<Request> {
+    Ok(
+        Request::authenticated(LOGS_PATH, "get-position", credentials)?
+            .param("showId", 1)
+            .param("showCoordinates", 1),
+    )
+}
+
+pub(crate) fn flight_log(
+    credentials: &Credentials,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+) -> Result<Request> {
+    let mut request =
+        Request::authenticated(LOGS_PATH, "get-logs", credentials)?
+            .param("showId", 1);
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            return Err(Error::InvalidParameter {
+                name: "startDateTime",
+                reason: "must not be after endDateTime".into(),
+            });
+        }
+    }
+    if let Some(start) = start {
+        request = request.param("startDateTime", start.format(DATE_FORMAT));
+    }
+    if let Some(end) = end {
+        request = request.param("endDateTime", end.format(DATE_FORMAT));
+    }
+    Ok(request)
+}
+
+pub(crate) fn server_status() -> Result<Request> {
+    Ok(Request::new(STATUS_PATH, "elite-server"))
+}
+
+pub(crate) fn discard() -> Result<Request> {
+    Ok(Request::new(JOURNAL_PATH, "discard"))
+}
+
+// The next batch of the journal's events, which is empty when there are none.
+pub(crate) fn journal(
+    credentials: &Credentials,
+    journal: &Journal,
+) -> Result<Request> {
+    let (software, version) = journal.software();
+    let events = journal.batch().unwrap_or(&[]);
+    let message =
+        serde_json::to_string(events).map_err(|e| Error::InvalidParameter {
+            name: "message",
+            reason: e.to_string(),
+        })?;
+
+    let mut request = Request::authenticated(JOURNAL_PATH, "", credentials)?
+        .param("fromSoftware", name("fromSoftware", software)?)
+        .param("fromSoftwareVersion", name("fromSoftwareVersion", version)?);
+    let state = journal.state();
+    if let Some(game_version) = &state.game_version {
+        request = request.param("fromGameVersion", game_version);
+    }
+    if let Some(game_build) = &state.game_build {
+        request = request.param("fromGameBuild", game_build);
+    }
+    request.idempotent = false;
+    Ok(request.param("message", message))
+}
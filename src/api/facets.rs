@@ -0,0 +1,40 @@
+/// Which of a [System][crate::System]'s separately fetched parts to include
+///
+/// Each facet is its own request to EDSM, merged into the system with
+/// [`System::merge`][crate::System::merge]. The default is none of them.
+///
+/// ```no_run
+/// use edsm::api::{Client, Facets, Show};
+///
+/// let facets = Facets { bodies: true, factions: true, ..Facets::default() };
+/// let sol = Client::new().system_with("Sol", Show::default(), facets).unwrap();
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Facets {
+    /// Fills `body_count` and `bodies`
+    pub bodies: bool,
+    /// Fills `stations`
+    pub stations: bool,
+    /// Fills `factions` and `controlling_faction`
+    pub factions: bool,
+    /// Also fills each faction's history, if `factions` is on
+    pub faction_history: bool,
+    /// Fills `traffic` and `traffic_breakdown`
+    pub traffic: bool,
+    /// Fills `deaths`
+    pub deaths: bool,
+}
+
+impl Facets {
+    /// Every facet, including faction history
+    pub fn all() -> Facets {
+        Facets {
+            bodies: true,
+            stations: true,
+            factions: true,
+            faction_history: true,
+            traffic: true,
+            deaths: true,
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use super::{DiscardList, Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error;
+use std::fmt;
+
+/// How many events are sent to EDSM in one request by default
+pub const DEFAULT_BATCH_SIZE: usize = 20;
+
+/// Where the commander was when an event happened
+///
+/// EDSM needs this context for events which don't say where they happened themselves. It's
+/// tracked from the events pushed into a [`Journal`], and sent along with every event as the
+/// `_systemAddress`, `_systemName`, `_systemCoordinates`, `_marketId`, `_stationName` and
+/// `_shipId` fields.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GameState {
+    pub system_address: Option<u64>,
+    pub system_name: Option<String>,
+    pub system_coordinates: Option<[f64; 3]>,
+    pub market_id: Option<u64>,
+    pub station_name: Option<String>,
+    pub ship_id: Option<u64>,
+    /// The `gameversion` from the journal's header, sent as `fromGameVersion`
+    pub game_version: Option<String>,
+    /// The `build` from the journal's header, sent as `fromGameBuild`
+    pub game_build: Option<String>,
+}
+
+impl GameState {
+    /// Track the commander's location and ship from a raw journal event
+    pub fn update(&mut self, event: &Map<String, Value>) {
+        let string =
+            |key| event.get(key).and_then(Value::as_str).map(String::from);
+        let number = |key| event.get(key).and_then(Value::as_u64);
+
+        match event.get("event").and_then(Value::as_str) {
+            Some("Fileheader") | Some("LoadGame") => {
+                if let Some(version) = string("gameversion") {
+                    self.game_version = Some(version);
+                }
+                if let Some(build) = string("build") {
+                    self.game_build = Some(build);
+                }
+                if let Some(ship_id) = number("ShipID") {
+                    self.ship_id = Some(ship_id);
+                }
+            }
+            Some("Location") | Some("FSDJump") | Some("CarrierJump") => {
+                self.system_address = number("SystemAddress");
+                self.system_name = string("StarSystem");
+                self.system_coordinates = event
+                    .get("StarPos")
+                    .and_then(Value::as_array)
+                    .and_then(|pos| match pos.as_slice() {
+                        [x, y, z] => {
+                            Some([x.as_f64()?, y.as_f64()?, z.as_f64()?])
+                        }
+                        _ => None,
+                    });
+                if event.get("Docked").and_then(Value::as_bool) == Some(true) {
+                    self.market_id = number("MarketID");
+                    self.station_name = string("StationName");
+                } else {
+                    self.market_id = None;
+                    self.station_name = None;
+                }
+            }
+            Some("Docked") => {
+                self.market_id = number("MarketID");
+                self.station_name = string("StationName");
+            }
+            Some("Undocked") => {
+                self.market_id = None;
+                self.station_name = None;
+            }
+            Some("Loadout")
+            | Some("ShipyardSwap")
+            | Some("SetUserShipName") => {
+                self.ship_id = number("ShipID");
+            }
+            _ => {}
+        }
+    }
+
+    // Add the transient fields EDSM reads the game state from.
+    fn annotate(&self, event: &mut Map<String, Value>) {
+        if let Some(address) = self.system_address {
+            event.insert("_systemAddress".into(), address.into());
+        }
+        if let Some(name) = &self.system_name {
+            event.insert("_systemName".into(), name.clone().into());
+        }
+        if let Some(coords) = self.system_coordinates {
+            event.insert("_systemCoordinates".into(), coords.to_vec().into());
+        }
+        if let Some(market_id) = self.market_id {
+            event.insert("_marketId".into(), market_id.into());
+        }
+        if let Some(name) = &self.station_name {
+            event.insert("_stationName".into(), name.clone().into());
+        }
+        if let Some(ship_id) = self.ship_id {
+            event.insert("_shipId".into(), ship_id.into());
+        }
+    }
+}
+
+/// Journal events waiting to be uploaded with the [Journal API](https://www.edsm.net/en/api-journal-v1)
+///
+/// Events are pushed in the order they were written to the journal, and each is annotated with
+/// the [`GameState`] at the time. Events on the [`DiscardList`] are dropped, as EDSM asks, using
+/// the bundled list unless another is given.
+/// Upload the pending events with [`Client::upload_journal`][super::Client::upload_journal].
+///
+/// ```no_run
+/// use edsm::api::{Client, Credentials, Journal};
+/// use serde_json::json;
+///
+/// let client = Client::builder()
+///     .credentials(Credentials::new("Jameson", "api key"))
+///     .build()
+///     .unwrap();
+/// let mut journal = Journal::new("My Companion", "1.0.0");
+/// journal.push(&json!({
+///     "timestamp": "2021-03-01T18:21:47Z",
+///     "event": "Docked",
+///     "StationName": "Daedalus",
+///     "MarketID": 128106744,
+/// })).unwrap();
+/// let results = client.upload_journal(&mut journal).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Journal {
+    software: String,
+    software_version: String,
+    batch_size: usize,
+    discard: DiscardList,
+    state: GameState,
+    pending: Vec<Value>,
+}
+
+impl Journal {
+    /// Start a journal uploaded as the named software, which EDSM requires
+    pub fn new<N, V>(software: N, software_version: V) -> Journal
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Journal {
+            software: software.into(),
+            software_version: software_version.into(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            discard: DiscardList::bundled(),
+            state: GameState::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Send at most this many events per request, defaults to [`DEFAULT_BATCH_SIZE`]
+    pub fn batch_size(mut self, batch_size: usize) -> Journal {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Drop events with these names instead of uploading them, on top of the discard list
+    pub fn discard<I, S>(mut self, events: I) -> Journal
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.discard.extend(events);
+        self
+    }
+
+    /// Replace the discard list, like with one fetched from EDSM
+    pub fn discard_list(mut self, discard: DiscardList) -> Journal {
+        self.discard = discard;
+        self
+    }
+
+    /// The game state the next event will be annotated with
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The events waiting to be uploaded
+    pub fn pending(&self) -> &[Value] {
+        &self.pending
+    }
+
+    /// Add an event to upload, returning `false` if it was discarded
+    ///
+    /// The event must serialize to a JSON object with an `event` name, like the lines of the
+    /// game's journal files. Discarded events still update the game state.
+    pub fn push<E: Serialize>(&mut self, event: &E) -> Result<bool> {
+        let invalid = |reason: String| Error::InvalidParameter {
+            name: "message",
+            reason,
+        };
+        let mut event = match serde_json::to_value(event) {
+            Ok(Value::Object(event)) => event,
+            Ok(_) => return Err(invalid("must be a JSON object".into())),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+        let name = match event.get("event").and_then(Value::as_str) {
+            Some(name) => name.to_owned(),
+            None => return Err(invalid("must have an event name".into())),
+        };
+
+        self.state.update(&event);
+        if self.discard.contains(&name) {
+            return Ok(false);
+        }
+        self.state.annotate(&mut event);
+        self.pending.push(Value::Object(event));
+        Ok(true)
+    }
+
+    // The next events to send, if any.
+    pub(crate) fn batch(&self) -> Option<&[Value]> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            let len = self.batch_size.min(self.pending.len());
+            Some(&self.pending[..len])
+        }
+    }
+
+    // Forget the given number of events once EDSM has them.
+    pub(crate) fn sent(&mut self, count: usize) {
+        self.pending.drain(..count);
+    }
+
+    pub(crate) fn software(&self) -> (&str, &str) {
+        (&self.software, &self.software_version)
+    }
+}
+
+/// What EDSM did with an uploaded event, from its `msgnum`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    /// `100`, the event was stored
+    Accepted,
+    /// `101`, the event was already stored
+    AlreadyStored,
+    /// `102`, EDSM has newer information
+    Outdated,
+    /// `103`, the event was sent twice
+    Duplicate,
+    /// `104`, events from another commander's ship aren't stored
+    CrewSession,
+    /// `3xx`, EDSM ignores this kind of event, or will process it later
+    Ignored,
+    /// Anything else, see the message for details
+    Failed,
+}
+
+impl EventStatus {
+    pub fn from_code(code: u16) -> EventStatus {
+        match code {
+            100 => EventStatus::Accepted,
+            101 => EventStatus::AlreadyStored,
+            102 => EventStatus::Outdated,
+            103 => EventStatus::Duplicate,
+            104 => EventStatus::CrewSession,
+            300..=399 => EventStatus::Ignored,
+            _ => EventStatus::Failed,
+        }
+    }
+}
+
+/// EDSM's response to a single uploaded event
+#[derive(Deserialize, Debug)]
+#[serde(from = "RawEventResult")]
+pub struct EventResult {
+    pub status: EventStatus,
+    /// EDSM's `msgnum`
+    pub code: u16,
+    pub message: String,
+    /// The EDSM id of the system the event was stored against
+    pub system_id: Option<u64>,
+}
+
+impl EventResult {
+    /// Whether EDSM has the event, even if it was already stored
+    pub fn is_ok(&self) -> bool {
+        (100..200).contains(&self.code)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawEventResult {
+    msgnum: u16,
+    msg: String,
+    #[serde(rename = "systemId")]
+    system_id: Option<u64>,
+}
+
+impl From<RawEventResult> for EventResult {
+    fn from(raw: RawEventResult) -> EventResult {
+        EventResult {
+            status: EventStatus::from_code(raw.msgnum),
+            code: raw.msgnum,
+            message: raw.msg,
+            system_id: raw.system_id,
+        }
+    }
+}
+
+/// A [Journal] upload which failed part way through
+///
+/// The events of earlier batches were already uploaded and removed from the journal, so their
+/// results are kept here.
+#[derive(Debug)]
+pub struct UploadError {
+    /// The results of the events uploaded before the error, in order
+    pub results: Vec<EventResult>,
+    pub error: Error,
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (after uploading {} events)",
+            self.error,
+            self.results.len()
+        )
+    }
+}
+
+impl error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<UploadError> for Error {
+    fn from(err: UploadError) -> Error {
+        err.error
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct JournalReply {
+    #[serde(default)]
+    pub events: Vec<EventResult>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn push() {
+        let mut journal = Journal::new("test", "0.0.0")
+            .batch_size(2)
+            .discard_list(DiscardList::default())
+            .discard(vec!["Music"]);
+        let jump = json!({
+            "event": "FSDJump",
+            "StarSystem": "Sol",
+            "SystemAddress": 10477373803u64,
+            "StarPos": [0.0, 0.0, 0.0],
+        });
+        assert!(journal.push(&jump).unwrap());
+        assert!(!journal.push(&json!({ "event": "Music" })).unwrap());
+        let docked = json!({
+            "event": "Docked",
+            "StationName": "Daedalus",
+            "MarketID": 128106744,
+        });
+        assert!(journal.push(&docked).unwrap());
+        assert!(journal.push(&json!({ "event": "Undocked" })).unwrap());
+        assert!(journal.push(&json!([])).is_err());
+
+        let pending = journal.pending();
+        assert_eq!(3, pending.len());
+        assert_eq!("Sol", pending[0]["_systemName"]);
+        assert_eq!(json!([0.0, 0.0, 0.0]), pending[1]["_systemCoordinates"]);
+        assert_eq!("Daedalus", pending[1]["_stationName"]);
+        assert!(pending[2].get("_stationName").is_none());
+
+        assert_eq!(2, journal.batch().unwrap().len());
+        journal.sent(2);
+        assert_eq!(1, journal.batch().unwrap().len());
+        journal.sent(1);
+        assert!(journal.batch().is_none());
+    }
+
+    #[test]
+    fn event_results() {
+        let json = r#"
+            {
+                "msgnum": 100,
+                "msg": "OK",
+                "events": [
+                    { "msgnum": 100, "msg": "Message queued", "systemId": 27 },
+                    { "msgnum": 101, "msg": "Message already stored" },
+                    { "msgnum": 302, "msg": "Event not yet processed" },
+                    { "msgnum": 402, "msg": "Item unknown" }
+                ]
+            }
+        "#;
+        let reply: JournalReply = serde_json::from_str(json).unwrap();
+        let statuses: Vec<_> = reply.events.iter().map(|e| e.status).collect();
+        assert_eq!(
+            vec![
+                EventStatus::Accepted,
+                EventStatus::AlreadyStored,
+                EventStatus::Ignored,
+                EventStatus::Failed,
+            ],
+            statuses
+        );
+        assert!(reply.events[1].is_ok());
+        assert!(!reply.events[3].is_ok());
+    }
+}
@@ -0,0 +1,198 @@
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// EDSM's rate limit, as reported by the `x-rate-limit-*` headers of a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed in a full window
+    pub limit: u64,
+    /// The number of requests left before EDSM starts responding with `429 Too Many Requests`
+    pub remaining: u64,
+    /// How long until `remaining` is back up to `limit`
+    pub reset: Duration,
+}
+
+impl RateLimit {
+    /// Parse the rate limit headers, returning `None` unless all of them are present
+    pub fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        let header = |name| -> Option<u64> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        };
+        Some(RateLimit {
+            limit: header("x-rate-limit-limit")?,
+            remaining: header("x-rate-limit-remaining")?,
+            reset: Duration::from_secs(header("x-rate-limit-reset")?),
+        })
+    }
+}
+
+/// A token bucket which spaces requests out to stay within EDSM's rate limit
+///
+/// The bucket starts full, allowing a burst of up to `requests` calls, and then refills at a rate
+/// of `requests` per `period`. Whenever EDSM reports its own [`RateLimit`] the bucket is corrected
+/// to never hold more tokens than the server says remain, so several processes sharing an IP
+/// still back off. When none remain, nothing is sent until EDSM's window resets.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    // When the tokens were last counted, or in the future while waiting for EDSM to reset.
+    updated: Instant,
+}
+
+impl Throttle {
+    /// Allow `requests` calls every `period`
+    ///
+    /// # Panics
+    ///
+    /// If `requests` or `period` is zero.
+    pub fn new(requests: u32, period: Duration) -> Throttle {
+        assert!(requests > 0, "throttle must allow some requests");
+        assert!(!period.is_zero(), "throttle period must be non-zero");
+        Throttle {
+            capacity: requests.into(),
+            per_second: f64::from(requests) / period.as_secs_f64(),
+            tokens: requests.into(),
+            updated: Instant::now(),
+        }
+    }
+
+    // Take a token, returning how long the caller must wait before it's actually available.
+    //
+    // The token is reserved immediately, so concurrent callers queue up behind each other
+    // instead of all waking at once.
+    fn acquire(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.;
+        let paused = self.updated.saturating_duration_since(now);
+        if self.tokens >= 0. {
+            paused
+        } else {
+            paused + Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+
+    fn observe(&mut self, rate_limit: &RateLimit, now: Instant) {
+        self.refill(now);
+        self.capacity = rate_limit.limit as f64;
+        if rate_limit.remaining == 0 {
+            // Sending any sooner would only be met with `429 Too Many Requests`, after which the
+            // whole limit is available again.
+            self.tokens = self.capacity;
+            self.updated = self.updated.max(now + rate_limit.reset);
+        } else {
+            self.tokens = self.tokens.min(rate_limit.remaining as f64);
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now <= self.updated {
+            return;
+        }
+        let elapsed = now - self.updated;
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_second)
+            .min(self.capacity);
+        self.updated = now;
+    }
+}
+
+// Rate limit state shared by every clone of a client.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    last: Mutex<Option<RateLimit>>,
+    throttle: Option<Mutex<Throttle>>,
+}
+
+impl Limiter {
+    pub fn new(throttle: Option<Throttle>) -> Limiter {
+        Limiter {
+            last: Mutex::new(None),
+            throttle: throttle.map(Mutex::new),
+        }
+    }
+
+    pub fn last(&self) -> Option<RateLimit> {
+        *self.last.lock().expect("rate limit lock")
+    }
+
+    /// How long to wait before sending the next request
+    pub fn acquire(&self) -> Duration {
+        match &self.throttle {
+            Some(t) => t.lock().expect("throttle lock").acquire(Instant::now()),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    /// Record the rate limit headers of a response
+    pub fn observe(&self, headers: &HeaderMap) {
+        if let Some(rate_limit) = RateLimit::from_headers(headers) {
+            if let Some(t) = &self.throttle {
+                t.lock()
+                    .expect("throttle lock")
+                    .observe(&rate_limit, Instant::now());
+            }
+            *self.last.lock().expect("rate limit lock") = Some(rate_limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-rate-limit-limit", "360".parse().unwrap());
+        headers.insert("x-rate-limit-remaining", "359".parse().unwrap());
+        assert_eq!(None, RateLimit::from_headers(&headers));
+        headers.insert("x-rate-limit-reset", "10".parse().unwrap());
+        assert_eq!(
+            Some(RateLimit {
+                limit: 360,
+                remaining: 359,
+                reset: Duration::from_secs(10),
+            }),
+            RateLimit::from_headers(&headers)
+        );
+    }
+
+    #[test]
+    fn throttle_bursts_then_waits() {
+        let mut throttle = Throttle::new(2, Duration::from_secs(10));
+        let now = throttle.updated;
+        assert_eq!(Duration::from_secs(0), throttle.acquire(now));
+        assert_eq!(Duration::from_secs(0), throttle.acquire(now));
+        assert_eq!(Duration::from_secs(5), throttle.acquire(now));
+        assert_eq!(Duration::from_secs(10), throttle.acquire(now));
+        let later = now + Duration::from_secs(10);
+        assert_eq!(Duration::from_secs(5), throttle.acquire(later));
+    }
+
+    #[test]
+    fn throttle_observes_server_limit() {
+        let mut throttle = Throttle::new(100, Duration::from_secs(100));
+        let now = throttle.updated;
+        let rate_limit = RateLimit {
+            limit: 100,
+            remaining: 0,
+            reset: Duration::from_secs(100),
+        };
+        throttle.observe(&rate_limit, now);
+        assert_eq!(Duration::from_secs(100), throttle.acquire(now));
+        let reset = now + Duration::from_secs(100);
+        assert_eq!(Duration::from_secs(0), throttle.acquire(reset));
+
+        let rate_limit = RateLimit {
+            remaining: 50,
+            ..rate_limit
+        };
+        let mut throttle = Throttle::new(100, Duration::from_secs(100));
+        let now = throttle.updated;
+        throttle.observe(&rate_limit, now);
+        assert_eq!(Duration::from_secs(0), throttle.acquire(now));
+        assert_eq!(49., throttle.tokens);
+    }
+}
@@ -0,0 +1,279 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// When and how often a failed request is sent again
+///
+/// Retries wait with exponential backoff, starting at the base delay and doubling after every
+/// attempt up to the maximum delay. With jitter enabled each delay is randomized between half and
+/// all of that value, so many clients failing at once don't retry in lockstep. When EDSM sends a
+/// `Retry-After` header it's used instead, unless disabled.
+///
+/// The default policy makes up to 3 attempts starting with a 1 second delay, and retries on
+/// `429 Too Many Requests`, `500 Internal Server Error`, `502 Bad Gateway`,
+/// `503 Service Unavailable`, `504 Gateway Timeout`, connection errors, timeouts, and connections
+/// dropped while sending the request or reading the response.
+///
+/// Journal uploads aren't idempotent, EDSM may have stored a batch before timing out or failing
+/// to respond. Unless [enabled][RetryPolicy::retry_non_idempotent] they're only retried when EDSM
+/// can't have processed them, after `429 Too Many Requests` or failing to connect.
+///
+/// ```
+/// use edsm::api::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(5)
+///     .backoff(Duration::from_secs(2), Duration::from_secs(120));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    statuses: Vec<StatusCode>,
+    retry_after: bool,
+    non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Never retry, every request is attempted exactly once
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::default().max_attempts(1)
+    }
+
+    /// The total number of attempts including the first, at least 1
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The delay before the first retry, and the most any retry will wait
+    pub fn backoff(mut self, base: Duration, max: Duration) -> RetryPolicy {
+        self.base_delay = base;
+        self.max_delay = max;
+        self
+    }
+
+    /// Randomize delays to spread out retries, enabled by default
+    pub fn jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Replace the set of response statuses which are retried
+    pub fn statuses<I>(mut self, statuses: I) -> RetryPolicy
+    where
+        I: IntoIterator<Item = StatusCode>,
+    {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Wait as long as EDSM's `Retry-After` header asks, enabled by default
+    ///
+    /// The requested wait is still capped by the maximum delay.
+    pub fn honor_retry_after(mut self, retry_after: bool) -> RetryPolicy {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Retry journal uploads after timeouts and server errors too, disabled by default
+    ///
+    /// This can upload a batch of events twice, which EDSM reports as duplicates.
+    pub fn retry_non_idempotent(mut self, retry: bool) -> RetryPolicy {
+        self.non_idempotent = retry;
+        self
+    }
+
+    // How long to wait before trying again after the given (1 based) attempt failed with a
+    // response, or `None` to give up.
+    pub(crate) fn after_status(
+        &self,
+        attempt: u32,
+        status: StatusCode,
+        headers: &HeaderMap,
+        idempotent: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.statuses.contains(&status) {
+            return None;
+        }
+        // Only a rate limited request certainly wasn't processed.
+        if !idempotent
+            && !self.non_idempotent
+            && status != StatusCode::TOO_MANY_REQUESTS
+        {
+            return None;
+        }
+        match retry_after(headers) {
+            Some(d) if self.retry_after => Some(d.min(self.max_delay)),
+            _ => Some(self.backoff_delay(attempt)),
+        }
+    }
+
+    // Like `after_status`, but for requests which failed without a response.
+    pub(crate) fn after_error(
+        &self,
+        attempt: u32,
+        error: &reqwest::Error,
+        idempotent: bool,
+    ) -> Option<Duration> {
+        // A request which couldn't connect was never sent, others may have been acted on.
+        // Bodies are decoded here rather than by `reqwest`, so its decode errors are from reading
+        // a truncated body.
+        let dropped = error.is_timeout()
+            || error.is_request()
+            || error.is_body()
+            || error.is_decode();
+        let retryable = error.is_connect()
+            || (dropped && (idempotent || self.non_idempotent));
+        if attempt < self.max_attempts && retryable {
+            Some(self.backoff_delay(attempt))
+        } else {
+            None
+        }
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31) as i32;
+        let delay = (self.base_delay.as_secs_f64() * 2f64.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        if self.jitter {
+            Duration::from_secs_f64(delay / 2. + delay / 2. * random())
+        } else {
+            Duration::from_secs_f64(delay)
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_after: true,
+            non_idempotent: false,
+        }
+    }
+}
+
+// Parse a `Retry-After` header, given either in seconds or as an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+// A random number in `[0, 1)`, good enough for jitter without another dependency.
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::default()
+            .max_attempts(10)
+            .jitter(false)
+            .backoff(Duration::from_secs(1), Duration::from_secs(5));
+        let headers = HeaderMap::new();
+        let delays: Vec<_> = (1..6)
+            .map(|a| {
+                policy
+                    .after_status(a, StatusCode::BAD_GATEWAY, &headers, true)
+                    .unwrap()
+                    .as_secs()
+            })
+            .collect();
+        assert_eq!(vec![1, 2, 4, 5, 5], delays);
+    }
+
+    #[test]
+    fn jitter_stays_within_half() {
+        let policy = RetryPolicy::default()
+            .backoff(Duration::from_secs(4), Duration::from_secs(60));
+        for _ in 0..100 {
+            let delay = policy.backoff_delay(1);
+            assert!(delay >= Duration::from_secs(2));
+            assert!(delay <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn gives_up() {
+        let policy = RetryPolicy::default().max_attempts(2);
+        let headers = HeaderMap::new();
+        assert!(policy
+            .after_status(1, StatusCode::NOT_FOUND, &headers, true)
+            .is_none());
+        assert!(policy
+            .after_status(1, StatusCode::SERVICE_UNAVAILABLE, &headers, true)
+            .is_some());
+        assert!(policy
+            .after_status(2, StatusCode::SERVICE_UNAVAILABLE, &headers, true)
+            .is_none());
+        assert!(RetryPolicy::none()
+            .after_status(1, StatusCode::SERVICE_UNAVAILABLE, &headers, true)
+            .is_none());
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "30".parse().unwrap());
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            policy.after_status(
+                1,
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers,
+                true
+            )
+        );
+        let policy = policy.honor_retry_after(false).jitter(false);
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            policy.after_status(
+                1,
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers,
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn non_idempotent() {
+        let headers = HeaderMap::new();
+        let policy = RetryPolicy::default();
+        let retry = |policy: &RetryPolicy, status| {
+            policy.after_status(1, status, &headers, false).is_some()
+        };
+        assert!(!retry(&policy, StatusCode::SERVICE_UNAVAILABLE));
+        assert!(retry(&policy, StatusCode::TOO_MANY_REQUESTS));
+        let policy = policy.retry_non_idempotent(true);
+        assert!(retry(&policy, StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
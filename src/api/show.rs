@@ -0,0 +1,81 @@
+/// Which optional parts of a [System][crate::System] EDSM should include
+///
+/// Each flag corresponds to one of EDSM's `show*` parameters. Leaving flags off makes responses
+/// smaller and faster, at the cost of the matching fields being `None`. The default is what this
+/// crate has always requested: everything but the primary star and hidden systems.
+///
+/// ```
+/// use edsm::api::Show;
+///
+/// let names_only = Show::none();
+/// let with_star = Show { primary_star: true, ..Show::default() };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Show {
+    /// `showId`, fills `id` and `id64`
+    pub id: bool,
+    /// `showCoordinates`, fills `coords` and `coords_locked`
+    pub coordinates: bool,
+    /// `showPermit`, fills `require_permit` and `permit_name`
+    pub permit: bool,
+    /// `showInformation`, fills `information`
+    pub information: bool,
+    /// `showPrimaryStar`, fills `primary_star`
+    pub primary_star: bool,
+    /// `includeHidden`, also returns systems EDSM hides because they're invalid or duplicates
+    pub include_hidden: bool,
+}
+
+impl Show {
+    /// Only the system names
+    pub fn none() -> Show {
+        Show {
+            id: false,
+            coordinates: false,
+            permit: false,
+            information: false,
+            primary_star: false,
+            include_hidden: false,
+        }
+    }
+
+    /// Every optional part, including hidden systems
+    pub fn all() -> Show {
+        Show {
+            id: true,
+            coordinates: true,
+            permit: true,
+            information: true,
+            primary_star: true,
+            include_hidden: true,
+        }
+    }
+
+    // The query parameters for each enabled flag
+    pub(crate) fn params(&self) -> impl Iterator<Item = &'static str> {
+        let flags = [
+            (self.id, "showId"),
+            (self.coordinates, "showCoordinates"),
+            (self.permit, "showPermit"),
+            (self.information, "showInformation"),
+            (self.primary_star, "showPrimaryStar"),
+            (self.include_hidden, "includeHidden"),
+        ];
+        IntoIterator::into_iter(flags)
+            .filter(|(on, _)| *on)
+            .map(|(_, p)| p)
+    }
+}
+
+impl Default for Show {
+    fn default() -> Show {
+        Show {
+            id: true,
+            coordinates: true,
+            permit: true,
+            information: true,
+            primary_star: false,
+            include_hidden: false,
+        }
+    }
+}
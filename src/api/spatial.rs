@@ -0,0 +1,139 @@
+use super::Show;
+use elite_journal::prelude::Coordinate;
+
+/// The middle of a sphere or cube search
+///
+/// Searches can be centered on a system EDSM knows by name, or on any point in space such as a
+/// fleet carrier's position or the middle of a nebula.
+#[derive(Debug)]
+pub enum Center {
+    Name(String),
+    Coords(Coordinate),
+}
+
+impl From<&str> for Center {
+    fn from(name: &str) -> Center {
+        Center::Name(name.into())
+    }
+}
+
+impl From<String> for Center {
+    fn from(name: String) -> Center {
+        Center::Name(name)
+    }
+}
+
+impl From<Coordinate> for Center {
+    fn from(coords: Coordinate) -> Center {
+        Center::Coords(coords)
+    }
+}
+
+/// The region of space a [`SpatialQuery`] covers
+///
+/// Any size left as `None` uses EDSM's default for that shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Systems within `radius` of the center, but no closer than `min_radius`
+    ///
+    /// The `radius` can be at most [`MAX_SPHERE_RADIUS`][super::MAX_SPHERE_RADIUS], and
+    /// `min_radius` can't be larger than it, or than
+    /// [`DEFAULT_SPHERE_RADIUS`][super::DEFAULT_SPHERE_RADIUS] without one.
+    Sphere {
+        radius: Option<f64>,
+        min_radius: Option<f64>,
+    },
+    /// Systems within an axis aligned cube with sides of length `size`
+    ///
+    /// The `size` can be at most [`MAX_CUBE_SIZE`][super::MAX_CUBE_SIZE].
+    Cube { size: Option<f64> },
+}
+
+/// A search for every [System][crate::System] within a region of space
+///
+/// All fields are public, so switching between a sphere and cube search is a matter of changing
+/// the `shape`. The query is validated when it's sent.
+///
+/// ```no_run
+/// use edsm::api::{Client, Shape, SpatialQuery};
+///
+/// let client = Client::new();
+/// let mut query = SpatialQuery::sphere("Sol").radius(20.);
+/// let sphere = client.systems_within(&query).unwrap();
+/// query.shape = Shape::Cube { size: Some(40.) };
+/// let cube = client.systems_within(&query).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SpatialQuery {
+    pub center: Center,
+    pub shape: Shape,
+    pub show: Show,
+}
+
+impl SpatialQuery {
+    pub fn new<C: Into<Center>>(center: C, shape: Shape) -> SpatialQuery {
+        SpatialQuery {
+            center: center.into(),
+            shape,
+            show: Show::default(),
+        }
+    }
+
+    /// Search a sphere of EDSM's default radius
+    pub fn sphere<C: Into<Center>>(center: C) -> SpatialQuery {
+        SpatialQuery::new(
+            center,
+            Shape::Sphere {
+                radius: None,
+                min_radius: None,
+            },
+        )
+    }
+
+    /// Search a cube of EDSM's default size
+    pub fn cube<C: Into<Center>>(center: C) -> SpatialQuery {
+        SpatialQuery::new(center, Shape::Cube { size: None })
+    }
+
+    /// Set the radius of a sphere search, turning this into one if needed
+    pub fn radius(mut self, radius: f64) -> SpatialQuery {
+        self.shape = match self.shape {
+            Shape::Sphere { min_radius, .. } => Shape::Sphere {
+                radius: Some(radius),
+                min_radius,
+            },
+            Shape::Cube { .. } => Shape::Sphere {
+                radius: Some(radius),
+                min_radius: None,
+            },
+        };
+        self
+    }
+
+    /// Set the minimum radius of a sphere search, turning this into one if needed
+    pub fn min_radius(mut self, min_radius: f64) -> SpatialQuery {
+        self.shape = match self.shape {
+            Shape::Sphere { radius, .. } => Shape::Sphere {
+                radius,
+                min_radius: Some(min_radius),
+            },
+            Shape::Cube { .. } => Shape::Sphere {
+                radius: None,
+                min_radius: Some(min_radius),
+            },
+        };
+        self
+    }
+
+    /// Set the size of a cube search, turning this into one if needed
+    pub fn size(mut self, size: f64) -> SpatialQuery {
+        self.shape = Shape::Cube { size: Some(size) };
+        self
+    }
+
+    /// Choose which parts of each system to include
+    pub fn show(mut self, show: Show) -> SpatialQuery {
+        self.show = show;
+        self
+    }
+}
@@ -0,0 +1,37 @@
+/// Which station's market, shipyard or outfitting to request
+///
+/// EDSM identifies these services by the station's market id, but also accepts the names of the
+/// system and station.
+///
+/// ```
+/// use edsm::api::StationRef;
+///
+/// let by_id = StationRef::from(128106744);
+/// let by_name = StationRef::from(("Sol", "Daedalus"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StationRef {
+    MarketId(u64),
+    Name { system: String, station: String },
+}
+
+impl From<u64> for StationRef {
+    fn from(market_id: u64) -> StationRef {
+        StationRef::MarketId(market_id)
+    }
+}
+
+impl From<(&str, &str)> for StationRef {
+    fn from((system, station): (&str, &str)) -> StationRef {
+        StationRef::Name {
+            system: system.into(),
+            station: station.into(),
+        }
+    }
+}
+
+impl From<(String, String)> for StationRef {
+    fn from((system, station): (String, String)) -> StationRef {
+        StationRef::Name { system, station }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::serde_utils::optional_space_seperated_datetime;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+/// Whether the Elite: Dangerous game servers are up, as last seen by EDSM
+#[derive(Deserialize, Debug)]
+pub struct ServerStatus {
+    #[serde(rename = "lastUpdate")]
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub last_update: Option<NaiveDateTime>,
+    #[serde(rename = "type")]
+    pub ty: StatusType,
+    /// Frontier's description of the status, like `OK` or `Upgrade in progress`
+    pub message: String,
+    /// `2` when up, `1` when busy and `0` when down
+    pub status: u8,
+}
+
+impl ServerStatus {
+    /// Whether the servers are accepting players, even if they're busy
+    pub fn is_up(&self) -> bool {
+        self.status > 0
+    }
+}
+
+/// How severe the [`ServerStatus`] is
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusType {
+    Success,
+    Warning,
+    Danger,
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_status() {
+        let json = r#"
+            {
+                "lastUpdate": "2021-03-01 18:21:47",
+                "type": "warning",
+                "message": "Upgrade in progress",
+                "status": 0
+            }
+        "#;
+        let status: ServerStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(StatusType::Warning, status.ty);
+        assert!(status.last_update.is_some());
+        assert!(!status.is_up());
+    }
+}
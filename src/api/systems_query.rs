@@ -0,0 +1,106 @@
+use super::Show;
+use chrono::NaiveDateTime;
+
+/// Whether EDSM knows where a system is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coordinates {
+    /// `onlyKnownCoordinates`
+    Known,
+    /// `onlyUnknownCoordinates`
+    Unknown,
+}
+
+/// A search for [Systems][crate::System] by name and when they were added to EDSM
+///
+/// Either a name or a date range is needed. Several names are looked up together, which saves
+/// requests against EDSM's rate limit. Dates are in UTC, like all of EDSM's times. As with
+/// [`SpatialQuery`][super::SpatialQuery] all fields are public, and the query is validated when
+/// it's sent.
+///
+/// ```no_run
+/// use chrono::{Duration, Utc};
+/// use edsm::api::{Client, SystemsQuery};
+///
+/// let yesterday = (Utc::now() - Duration::days(1)).naive_utc();
+/// let query = SystemsQuery::new().since(yesterday).known_coordinates();
+/// let discovered = Client::new().systems_matching(&query).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct SystemsQuery {
+    /// Names to look up, a single name also matches by prefix unless `exact` is set
+    pub names: Vec<String>,
+    /// Match the names exactly, which is always the case for more than one name
+    pub exact: bool,
+    pub coordinates: Option<Coordinates>,
+    /// Only systems created at or after this time
+    pub start: Option<NaiveDateTime>,
+    /// Only systems created at or before this time
+    pub end: Option<NaiveDateTime>,
+    /// Only featured systems, EDSM doesn't allow this with [`Show::information`]
+    pub only_featured: bool,
+    pub show: Show,
+}
+
+impl SystemsQuery {
+    /// Match every system, which needs at least a date range
+    pub fn new() -> SystemsQuery {
+        SystemsQuery::default()
+    }
+
+    /// Match systems by name (or prefix)
+    pub fn name<S: Into<String>>(name: S) -> SystemsQuery {
+        SystemsQuery {
+            exact: false,
+            ..SystemsQuery::names(vec![name])
+        }
+    }
+
+    /// Match each of the named systems exactly
+    pub fn names<I, S>(names: I) -> SystemsQuery
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        SystemsQuery {
+            names: names.into_iter().map(Into::into).collect(),
+            exact: true,
+            ..SystemsQuery::default()
+        }
+    }
+
+    /// Only systems with known coordinates
+    pub fn known_coordinates(mut self) -> SystemsQuery {
+        self.coordinates = Some(Coordinates::Known);
+        self
+    }
+
+    /// Only systems without known coordinates
+    pub fn unknown_coordinates(mut self) -> SystemsQuery {
+        self.coordinates = Some(Coordinates::Unknown);
+        self
+    }
+
+    /// Only systems created at or after the given time
+    pub fn since(mut self, start: NaiveDateTime) -> SystemsQuery {
+        self.start = Some(start);
+        self
+    }
+
+    /// Only systems created at or before the given time
+    pub fn until(mut self, end: NaiveDateTime) -> SystemsQuery {
+        self.end = Some(end);
+        self
+    }
+
+    /// Only featured systems
+    pub fn featured(mut self) -> SystemsQuery {
+        self.only_featured = true;
+        self
+    }
+
+    /// Choose which parts of each system to include
+    pub fn show(mut self, show: Show) -> SystemsQuery {
+        self.show = show;
+        self
+    }
+}
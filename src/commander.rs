@@ -0,0 +1,194 @@
+use crate::serde_utils::{
+    optional_space_seperated_datetime, space_seperated_datetime,
+};
+use chrono::NaiveDateTime;
+use elite_journal::prelude::Coordinate;
+use serde::Deserialize;
+
+/// A commander's ranks, along with the progress towards the next of each
+#[derive(Deserialize, Debug)]
+pub struct Ranks {
+    pub ranks: RankSet<u8>,
+    /// Percent progress towards the next rank
+    pub progress: RankSet<u8>,
+    /// Rank names, like `Elite` or `Rear Admiral`
+    #[serde(rename = "ranksVerbose")]
+    pub names: RankSet<String>,
+}
+
+/// One value for each of the game's rank ladders
+#[derive(Deserialize, Debug)]
+pub struct RankSet<T> {
+    #[serde(rename = "Combat")]
+    pub combat: T,
+    #[serde(rename = "Trade")]
+    pub trade: T,
+    #[serde(rename = "Explore")]
+    pub explore: T,
+    #[serde(rename = "CQC")]
+    pub cqc: T,
+    #[serde(rename = "Federation")]
+    pub federation: T,
+    #[serde(rename = "Empire")]
+    pub empire: T,
+    /// Only reported since Odyssey
+    #[serde(rename = "Soldier")]
+    pub soldier: Option<T>,
+    /// Only reported since Odyssey
+    #[serde(rename = "Exobiologist")]
+    pub exobiologist: Option<T>,
+}
+
+/// A commander's credit balance at a point in time
+#[derive(Deserialize, Debug)]
+pub struct Credits {
+    pub balance: i64,
+    pub loan: i64,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub date: Option<NaiveDateTime>,
+}
+
+/// A stack of materials, data or cargo a commander is carrying
+#[derive(Deserialize, Debug)]
+pub struct InventoryItem {
+    /// The game's internal name, like `antimony`
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub name: String,
+    #[serde(rename = "qty")]
+    pub quantity: u64,
+}
+
+/// Where a commander was last seen
+///
+/// Everything is `None` when the commander doesn't share their flight log publicly.
+#[derive(Deserialize, Debug)]
+pub struct Position {
+    /// The name of the commander's current [System][crate::System]
+    pub system: Option<String>,
+    #[serde(rename = "systemId")]
+    pub system_id: Option<u64>,
+    #[serde(rename = "systemId64")]
+    pub system_id64: Option<u64>,
+    pub coordinates: Option<Coordinate>,
+    #[serde(rename = "firstDiscover")]
+    pub first_discover: Option<bool>,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub date: Option<NaiveDateTime>,
+
+    #[serde(rename = "isDocked")]
+    #[serde(default)]
+    pub is_docked: bool,
+    pub station: Option<String>,
+    #[serde(rename = "dateDocked")]
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub date_docked: Option<NaiveDateTime>,
+
+    #[serde(rename = "shipId")]
+    pub ship_id: Option<u64>,
+    #[serde(rename = "shipType")]
+    pub ship_type: Option<String>,
+}
+
+/// A jump in a commander's flight log
+#[derive(Deserialize, Debug)]
+pub struct FlightLogEntry {
+    #[serde(rename = "shipId")]
+    pub ship_id: Option<u64>,
+    pub system: String,
+    #[serde(rename = "systemId")]
+    pub system_id: Option<u64>,
+    #[serde(rename = "systemId64")]
+    pub system_id64: Option<u64>,
+    #[serde(rename = "firstDiscover")]
+    #[serde(default)]
+    pub first_discover: bool,
+    #[serde(deserialize_with = "space_seperated_datetime")]
+    pub date: NaiveDateTime,
+}
+
+// The bodies of responses wrapping a list.
+
+#[derive(Deserialize)]
+pub(crate) struct CreditsReply {
+    pub credits: Vec<Credits>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct InventoryReply {
+    // Named after the requested type.
+    #[serde(alias = "data")]
+    #[serde(alias = "cargo")]
+    pub materials: Vec<InventoryItem>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FlightLogReply {
+    #[serde(default)]
+    pub logs: Vec<FlightLogEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks() {
+        let json = r#"
+            {
+                "msgnum": 100,
+                "msg": "OK",
+                "ranks": {
+                    "Combat": 8, "Trade": 5, "Explore": 8, "CQC": 0,
+                    "Federation": 12, "Empire": 3
+                },
+                "progress": {
+                    "Combat": 0, "Trade": 46, "Explore": 0, "CQC": 0,
+                    "Federation": 100, "Empire": 21
+                },
+                "ranksVerbose": {
+                    "Combat": "Elite", "Trade": "Dealer", "Explore": "Elite",
+                    "CQC": "Helpless", "Federation": "Admiral", "Empire": "Squire"
+                }
+            }
+        "#;
+        let ranks: Ranks = serde_json::from_str(json).unwrap();
+        assert_eq!(8, ranks.ranks.combat);
+        assert_eq!(46, ranks.progress.trade);
+        assert_eq!("Admiral", ranks.names.federation);
+        assert!(ranks.ranks.soldier.is_none());
+    }
+
+    #[test]
+    fn position() {
+        let json = r#"
+            {
+                "msgnum": 100,
+                "msg": "OK",
+                "system": "Sol",
+                "firstDiscover": false,
+                "date": "2021-03-01 18:21:47",
+                "systemId": 27,
+                "systemId64": 10477373803,
+                "coordinates": { "x": 0, "y": 0, "z": 0 },
+                "isDocked": true,
+                "station": "Daedalus",
+                "dateDocked": "2021-03-01 18:25:02",
+                "shipId": 4,
+                "shipType": "Krait Phantom"
+            }
+        "#;
+        let position: Position = serde_json::from_str(json).unwrap();
+        assert_eq!(Some("Sol"), position.system.as_deref());
+        assert!(position.is_docked);
+        assert!(position.date_docked.is_some());
+
+        let private = r#"{ "msgnum": 100, "msg": "OK", "system": null }"#;
+        let position: Position = serde_json::from_str(private).unwrap();
+        assert!(position.system.is_none());
+        assert!(!position.is_docked);
+    }
+}
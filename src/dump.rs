@@ -0,0 +1,563 @@
+use crate::System;
+use flate2::bufread::MultiGzDecoder;
+use serde::de::DeserializeOwned;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::marker::PhantomData;
+#[cfg(feature = "rayon")]
+use std::mem;
+use std::path::Path;
+
+mod records;
+pub use self::records::{
+    CodexEntry, PowerPlay, StationBody, SystemBody, SystemStation,
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Every gzip stream starts with this, where JSON can't.
+const GZIP_MAGIC: u8 = 0x1f;
+
+/// A reader over a dump file, decompressing it if needed
+pub type FileReader<T> = Reader<Input<BufReader<File>>, T>;
+
+/// Read `systemsWithCoordinates.json`, `systemsWithoutCoordinates.json`,
+/// `systemsPopulated.json` or `systemsWithCoordinates7days.json`
+///
+/// Systems from `systemsPopulated.json` include their factions, stations and bodies.
+pub fn systems<P: AsRef<Path>>(path: P) -> io::Result<FileReader<System>> {
+    Reader::open(path)
+}
+
+/// Read `bodies.json` or `bodies7days.json`
+pub fn bodies<P: AsRef<Path>>(path: P) -> io::Result<FileReader<SystemBody>> {
+    Reader::open(path)
+}
+
+/// Read `stations.json`
+pub fn stations<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<FileReader<SystemStation>> {
+    Reader::open(path)
+}
+
+/// Read `powerPlay.json`
+pub fn power_play<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<FileReader<PowerPlay>> {
+    Reader::open(path)
+}
+
+/// Read `codex.json`
+pub fn codex<P: AsRef<Path>>(path: P) -> io::Result<FileReader<CodexEntry>> {
+    Reader::open(path)
+}
+
+// How many records are parsed in parallel while the next are read.
+#[cfg(feature = "rayon")]
+const CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the dump failed, no more records will be read
+    Io(io::Error),
+    /// A record wasn't the JSON we expected, the records after it are still read
+    Record {
+        /// The (1 based) line of the offending value
+        line: usize,
+        /// The path to the offending value, like `bodies[3].orbitalPeriod`
+        path: String,
+        source: serde_json::Error,
+    },
+    /// Something other than a record was found between records
+    Syntax { line: usize, found: char },
+    /// The dump ended in the middle of the record starting on the given line
+    Truncated { line: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Record { line, path, .. } => {
+                write!(f, "unexpected JSON at `{}` on line {}", path, line)
+            }
+            Error::Syntax { line, found } => {
+                write!(
+                    f,
+                    "unexpected `{}` between records on line {}",
+                    found, line
+                )
+            }
+            Error::Truncated { line } => {
+                write!(f, "truncated record starting on line {}", line)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Record { ref source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// A dump's bytes, decompressed as they're read if they're gzipped
+#[derive(Debug)]
+pub struct Input<R>(Inner<R>);
+
+#[derive(Debug)]
+enum Inner<R> {
+    Plain(R),
+    // EDSM's dumps may be made of more than one gzip member.
+    Gzip(BufReader<MultiGzDecoder<R>>),
+}
+
+impl<R: BufRead> Input<R> {
+    /// Check whether the reader is gzipped, without consuming anything
+    pub fn new(mut reader: R) -> io::Result<Input<R>> {
+        let gzipped = loop {
+            match reader.fill_buf() {
+                Ok(buf) => break buf.first() == Some(&GZIP_MAGIC),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(Input(if gzipped {
+            Inner::Gzip(BufReader::new(MultiGzDecoder::new(reader)))
+        } else {
+            Inner::Plain(reader)
+        }))
+    }
+
+    pub fn is_gzipped(&self) -> bool {
+        matches!(self.0, Inner::Gzip(_))
+    }
+}
+
+impl<R: BufRead> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            Inner::Plain(r) => r.read(buf),
+            Inner::Gzip(r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Input<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match &mut self.0 {
+            Inner::Plain(r) => r.fill_buf(),
+            Inner::Gzip(r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.0 {
+            Inner::Plain(r) => r.consume(amt),
+            Inner::Gzip(r) => r.consume(amt),
+        }
+    }
+}
+
+/// Read the records of an EDSM dump one at a time
+///
+/// Dumps are a JSON array of records, which can be many gigabytes. Only one record is held in
+/// memory at once, whether the dump has a record per line or is pretty printed. Records which
+/// fail to parse are reported with their line number, without stopping the rest from being
+/// read.
+///
+/// Dumps opened from a path or [any reader][Reader::from_reader] may be gzipped, like the
+/// `.json.gz` files EDSM publishes, and are decompressed as they're read.
+///
+/// ```no_run
+/// use edsm::dump::Reader;
+///
+/// let reader: Reader<_> = Reader::open("dumps/systemsWithCoordinates.json.gz").unwrap();
+/// for system in reader {
+///     match system {
+///         Ok(system) => println!("{}", system.name),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Reader<R, T = System> {
+    reader: R,
+    // The current (1 based) line, and the line the current record started on.
+    line: usize,
+    start: usize,
+    record: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    done: bool,
+    records: PhantomData<fn() -> T>,
+}
+
+impl<T> Reader<Input<BufReader<File>>, T> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Reader::from_reader(File::open(path)?)
+    }
+}
+
+impl<R: Read, T> Reader<Input<BufReader<R>>, T> {
+    /// Read a dump from anywhere, like a network stream, gzipped or not
+    pub fn from_reader(reader: R) -> io::Result<Self> {
+        Ok(Reader::new(Input::new(BufReader::new(reader))?))
+    }
+}
+
+impl<R: BufRead, T> Reader<R, T> {
+    /// Read an uncompressed dump
+    pub fn new(reader: R) -> Self {
+        Reader {
+            reader,
+            line: 1,
+            start: 1,
+            record: Vec::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            done: false,
+            records: PhantomData,
+        }
+    }
+
+    /// The line the reader has reached
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // The bytes of the next record, with the line it started on.
+    fn next_record(&mut self) -> Option<Result<usize>> {
+        loop {
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            if chunk.is_empty() {
+                self.done = true;
+                return if self.depth > 0 {
+                    Some(Err(Error::Truncated { line: self.start }))
+                } else {
+                    None
+                };
+            }
+
+            let mut used = 0;
+            let mut found = None;
+            for &byte in chunk {
+                used += 1;
+                if byte == b'\n' {
+                    self.line += 1;
+                }
+
+                if self.depth == 0 {
+                    match byte {
+                        b'{' => {
+                            self.start = self.line;
+                            self.depth = 1;
+                            self.record.clear();
+                            self.record.push(byte);
+                        }
+                        // The array around the records, and the commas between them.
+                        b'[' | b']' | b',' => {}
+                        b if b.is_ascii_whitespace() => {}
+                        b => {
+                            found = Some(Err(Error::Syntax {
+                                line: self.line,
+                                found: b as char,
+                            }));
+                            break;
+                        }
+                    }
+                    continue;
+                }
+
+                self.record.push(byte);
+                if self.in_string {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if byte == b'\\' {
+                        self.escaped = true;
+                    } else if byte == b'"' {
+                        self.in_string = false;
+                    }
+                } else {
+                    match byte {
+                        b'"' => self.in_string = true,
+                        b'{' => self.depth += 1,
+                        b'}' => {
+                            self.depth -= 1;
+                            if self.depth == 0 {
+                                found = Some(Ok(self.start));
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            self.reader.consume(used);
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for Reader<R, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.done {
+            return None;
+        }
+        match self.next_record()? {
+            Ok(start) => Some(deserialize(start, &self.record)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Parsing on every core, with the `rayon` feature
+///
+/// Records are still read in order on the calling thread, but parsed in parallel, a chunk at a
+/// time, while the next chunk is read. Handing records to a channel instead of collecting them
+/// keeps memory flat.
+///
+/// ```no_run
+/// use edsm::dump::Reader;
+/// use std::sync::mpsc;
+/// use std::thread;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// thread::spawn(move || {
+///     let reader: Reader<_> = Reader::open("dumps/systemsWithCoordinates.json.gz").unwrap();
+///     reader.par_for_each(|system| sender.send(system).unwrap());
+/// });
+/// for system in receiver {
+///     println!("{}", system.unwrap().name);
+/// }
+/// ```
+#[cfg(feature = "rayon")]
+impl<R, T> Reader<R, T>
+where
+    R: BufRead + Send,
+    T: DeserializeOwned + Send,
+{
+    /// Call `f` with each record as soon as it's parsed, in any order
+    pub fn par_for_each<F>(self, f: F)
+    where
+        F: Fn(Result<T>) + Send + Sync,
+    {
+        use rayon::prelude::*;
+
+        self.par_chunks(|chunk| {
+            chunk.into_par_iter().for_each(|raw| f(parse(raw)))
+        })
+    }
+
+    /// Call `f` with each record in the order they're in the dump
+    pub fn par_for_each_ordered<F>(self, mut f: F)
+    where
+        F: FnMut(Result<T>) + Send,
+    {
+        use rayon::prelude::*;
+
+        self.par_chunks(|chunk| {
+            let parsed: Vec<_> = chunk.into_par_iter().map(parse).collect();
+            parsed.into_iter().for_each(&mut f)
+        })
+    }
+
+    /// Parse every record, in the order they're in the dump
+    pub fn par_collect(self) -> Vec<Result<T>> {
+        let mut records = Vec::new();
+        self.par_for_each_ordered(|record| records.push(record));
+        records
+    }
+
+    // Handle each chunk of raw records while the next is read.
+    fn par_chunks<F>(mut self, mut handle: F)
+    where
+        F: FnMut(Vec<Result<(usize, Vec<u8>)>>) + Send,
+    {
+        let mut chunk = self.chunk();
+        while !chunk.is_empty() {
+            let (next, ()) = rayon::join(|| self.chunk(), || handle(chunk));
+            chunk = next;
+        }
+    }
+
+    // The next records, unparsed, with the line each starts on.
+    fn chunk(&mut self) -> Vec<Result<(usize, Vec<u8>)>> {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        while chunk.len() < CHUNK_SIZE && !self.done {
+            match self.next_record() {
+                Some(Ok(start)) => {
+                    chunk.push(Ok((start, mem::take(&mut self.record))))
+                }
+                Some(Err(e)) => chunk.push(Err(e)),
+                None => break,
+            }
+        }
+        chunk
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn parse<T: DeserializeOwned>(raw: Result<(usize, Vec<u8>)>) -> Result<T> {
+    raw.and_then(|(start, record)| deserialize(start, &record))
+}
+
+// Parse a record, which starts on the given line of the dump.
+fn deserialize<T: DeserializeOwned>(start: usize, record: &[u8]) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(record);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let source = e.into_inner();
+        Error::Record {
+            line: start + source.line().saturating_sub(1),
+            path,
+            source,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Record {
+        id: u64,
+        name: String,
+    }
+
+    fn read(dump: &str) -> Vec<Result<Record>> {
+        Reader::new(dump.as_bytes()).collect()
+    }
+
+    #[test]
+    fn line_per_record() {
+        let dump = r#"[
+            {"id":1,"name":"Sol"},
+            {"id":2,"name":"Alpha { Centauri"}
+        ]"#;
+        let records = read(dump);
+        assert_eq!(2, records.len());
+        assert_eq!("Alpha { Centauri", records[1].as_ref().unwrap().name);
+    }
+
+    #[test]
+    fn pretty_printed() {
+        let dump = r#"[
+          {
+            "id": 1,
+            "name": "Sol \"}\"",
+            "coords": { "x": 0, "y": 0, "z": 0 }
+          },
+          {
+            "id": 2,
+            "name": "Achenar"
+          }
+        ]"#;
+        let records = read(dump);
+        assert_eq!(r#"Sol "}""#, records[0].as_ref().unwrap().name);
+        assert_eq!(2, records[1].as_ref().unwrap().id);
+    }
+
+    #[test]
+    fn gzipped() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let dump =
+            "[\n{\"id\":1,\"name\":\"Sol\"},\n{\"id\":2,\"name\":\"Lave\"}\n]";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(dump.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let reader = Reader::from_reader(&gzipped[..]).unwrap();
+        let records: Vec<Record> = reader.map(Result::unwrap).collect();
+        assert_eq!("Lave", records[1].name);
+
+        let input = Input::new(dump.as_bytes()).unwrap();
+        assert!(!input.is_gzipped());
+        let records: Vec<Record> =
+            Reader::new(input).map(Result::unwrap).collect();
+        assert_eq!(2, records.len());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let mut dump = String::from("[\n");
+        for id in 0..10_000 {
+            dump.push_str(&format!("{{\"id\":{},\"name\":\"{}\"}},\n", id, id));
+        }
+        dump.push_str("{\"id\":\"bad\"}\n]");
+
+        let records: Vec<Result<Record>> =
+            Reader::new(dump.as_bytes()).par_collect();
+        assert_eq!(10_001, records.len());
+        for (id, record) in records[..10_000].iter().enumerate() {
+            assert_eq!(id as u64, record.as_ref().unwrap().id);
+        }
+        assert!(matches!(
+            records[10_000],
+            Err(Error::Record { line: 10_002, .. })
+        ));
+
+        let sum = AtomicU64::new(0);
+        Reader::new(dump.as_bytes()).par_for_each(|record: Result<Record>| {
+            if let Ok(record) = record {
+                sum.fetch_add(record.id, Ordering::Relaxed);
+            }
+        });
+        assert_eq!((0..10_000).sum::<u64>(), sum.into_inner());
+    }
+
+    #[test]
+    fn errors() {
+        let dump = "[\n{\"id\":1,\"name\":\"Sol\"},\n{\"id\":\"two\",\n\"name\":\"Achenar\"},\nx\n{\"id\":3,\"name\":\"Lave\"},\n{\"id\":4,";
+        let records = read(dump);
+        assert_eq!(5, records.len());
+        assert!(records[0].is_ok());
+        assert!(matches!(
+            &records[1],
+            Err(Error::Record { line: 3, path, .. }) if path == "id"
+        ));
+        assert!(matches!(
+            records[2],
+            Err(Error::Syntax {
+                line: 5,
+                found: 'x'
+            })
+        ));
+        assert!(records[3].is_ok());
+        assert!(matches!(records[4], Err(Error::Truncated { line: 7 })));
+    }
+}
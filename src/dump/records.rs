@@ -0,0 +1,96 @@
+use crate::market::{Commodity, Module, Ship};
+use crate::serde_utils::optional_space_seperated_datetime;
+use crate::{Body, Station};
+use chrono::NaiveDateTime;
+use elite_journal::prelude::{
+    Allegiance, Coordinate, Government, State as JournalState,
+};
+use serde::Deserialize;
+
+/// A [Body] from `bodies.json` or `bodies7days.json`, with the system it's in
+#[derive(Deserialize, Debug)]
+pub struct SystemBody {
+    #[serde(rename = "systemId")]
+    pub system_id: Option<u64>,
+    #[serde(rename = "systemId64")]
+    pub system_id64: Option<u64>,
+    #[serde(rename = "systemName")]
+    pub system_name: String,
+    #[serde(flatten)]
+    pub body: Body,
+}
+
+/// A [Station] from `stations.json`, with the system it's in and what it sells
+#[derive(Deserialize, Debug)]
+pub struct SystemStation {
+    #[serde(rename = "systemId")]
+    pub system_id: Option<u64>,
+    #[serde(rename = "systemId64")]
+    pub system_id64: Option<u64>,
+    #[serde(rename = "systemName")]
+    pub system_name: String,
+    /// The body the station orbits or is landed on, if known
+    pub body: Option<StationBody>,
+    #[serde(flatten)]
+    pub station: Station,
+
+    pub commodities: Option<Vec<Commodity>>,
+    pub ships: Option<Vec<Ship>>,
+    #[serde(rename = "outfitting")]
+    pub modules: Option<Vec<Module>>,
+}
+
+/// The body a [SystemStation] is at
+#[derive(Deserialize, Debug)]
+pub struct StationBody {
+    pub id: u64,
+    pub name: String,
+    /// Set for surface stations and settlements
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A system's place in power play, from `powerPlay.json`
+#[derive(Deserialize, Debug)]
+pub struct PowerPlay {
+    /// The power, like `Zachary Hudson`
+    pub power: String,
+    /// Like `Control`, `Exploited` or `Contested`
+    #[serde(rename = "powerState")]
+    pub power_state: String,
+
+    /// The EDSM id of the system
+    pub id: u64,
+    pub id64: Option<u64>,
+    /// The name of the system
+    pub name: String,
+    pub coords: Option<Coordinate>,
+    pub allegiance: Option<Allegiance>,
+    pub government: Option<Government>,
+    pub state: Option<JournalState>,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub date: Option<NaiveDateTime>,
+}
+
+/// A codex entry reported in a system, from `codex.json`
+#[derive(Deserialize, Debug)]
+pub struct CodexEntry {
+    #[serde(rename = "systemId")]
+    pub system_id: Option<u64>,
+    #[serde(rename = "systemId64")]
+    pub system_id64: Option<u64>,
+    #[serde(rename = "systemName")]
+    pub system_name: String,
+    /// The galactic region, like `Inner Orion Spur`
+    pub region: Option<String>,
+    /// The kind of entry, like `Bark Mounds`
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The game's internal name for the entry
+    pub name: Option<String>,
+    #[serde(rename = "reportedOn")]
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub reported_on: Option<NaiveDateTime>,
+}
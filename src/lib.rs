@@ -1,6 +1,3 @@
-use std::fs::File;
-use std::io::BufReader;
-
 mod serde_utils;
 
 /// Faction information and state tracking
@@ -18,23 +15,45 @@ use self::faction::*;
 
 /// A star system in space, containing the bodies, factions, stations, etc
 pub mod system;
-pub use self::system::System;
+pub use self::system::{EstimatedValue, PrimaryStar, System, ValuableBody};
 
 /// The bodies of a system, including it's stars, planets, moons, rings, etc
 pub mod body;
 pub use self::body::{Belt, Body, Orbit};
 
+/// The stations of a system, including starports, outposts, settlements and fleet carriers
+pub mod station;
+pub use self::station::Station;
+
+/// The commodities, ships and modules for sale at a station
+pub mod market;
+pub use self::market::{Market, Outfitting, Shipyard};
+
+/// A commander's ranks, credits, inventory and flight log
+pub mod commander;
+pub use self::commander::{
+    Credits, FlightLogEntry, InventoryItem, Position, Ranks,
+};
+
+/// Ranked name search over systems from the API or a dump
+pub mod search;
+
+/// Streaming readers for EDSM's nightly dumps
+pub mod dump;
+
 /// The EDSM web API
 pub mod api;
 
-// TODO
-// Require a market ID, someone some stations have.
-// /market
-// /shipyard
-// /outfitting
+/// Recorded EDSM responses served from localhost, for testing without the network
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
+/// Load every system in a dump, panicking on any error
+///
+/// Prefer [`dump::Reader`] for the larger dumps, which doesn't hold every system in memory.
 pub fn json(file_path: &str) -> Vec<System> {
-    let file = File::open(file_path).unwrap();
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).unwrap()
+    dump::Reader::open(file_path)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
 }
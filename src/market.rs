@@ -0,0 +1,127 @@
+use serde::Deserialize;
+
+/// The commodities bought and sold at a [Station][crate::Station]
+#[derive(Deserialize, Debug)]
+pub struct Market {
+    /// The EDSM id of the station's system
+    pub id: u64,
+    pub id64: Option<u64>,
+    /// The name of the station's system
+    pub name: String,
+    #[serde(rename = "marketId")]
+    pub market_id: u64,
+    /// The EDSM id of the station
+    #[serde(rename = "sId")]
+    pub station_id: u64,
+    #[serde(rename = "sName")]
+    pub station_name: String,
+    pub url: Option<String>,
+    pub commodities: Vec<Commodity>,
+}
+
+/// A commodity's prices and supply at a [Market]
+#[derive(Deserialize, Debug)]
+pub struct Commodity {
+    /// The game's internal name, like `advancedcatalysers`
+    pub id: String,
+    pub name: String,
+    /// Credits to buy one unit, zero if the market doesn't sell it
+    #[serde(rename = "buyPrice")]
+    pub buy_price: u64,
+    pub stock: u64,
+    /// Credits for selling one unit
+    #[serde(rename = "sellPrice")]
+    pub sell_price: u64,
+    pub demand: u64,
+    #[serde(rename = "stockBracket")]
+    pub stock_bracket: Option<u8>,
+}
+
+/// The ships for sale at a [Station][crate::Station]
+#[derive(Deserialize, Debug)]
+pub struct Shipyard {
+    /// The EDSM id of the station's system
+    pub id: u64,
+    pub id64: Option<u64>,
+    /// The name of the station's system
+    pub name: String,
+    #[serde(rename = "marketId")]
+    pub market_id: u64,
+    /// The EDSM id of the station
+    #[serde(rename = "sId")]
+    pub station_id: u64,
+    #[serde(rename = "sName")]
+    pub station_name: String,
+    pub url: Option<String>,
+    pub ships: Vec<Ship>,
+}
+
+/// A ship for sale in a [Shipyard]
+#[derive(Deserialize, Debug)]
+pub struct Ship {
+    /// The game's internal id
+    pub id: u64,
+    pub name: String,
+}
+
+/// The modules for sale at a [Station][crate::Station]
+#[derive(Deserialize, Debug)]
+pub struct Outfitting {
+    /// The EDSM id of the station's system
+    pub id: u64,
+    pub id64: Option<u64>,
+    /// The name of the station's system
+    pub name: String,
+    #[serde(rename = "marketId")]
+    pub market_id: u64,
+    /// The EDSM id of the station
+    #[serde(rename = "sId")]
+    pub station_id: u64,
+    #[serde(rename = "sName")]
+    pub station_name: String,
+    pub url: Option<String>,
+    #[serde(rename = "outfitting")]
+    pub modules: Vec<Module>,
+}
+
+/// A module for sale in an [Outfitting] service
+#[derive(Deserialize, Debug)]
+pub struct Module {
+    /// The game's internal name, like `int_engine_size2_class1`
+    pub id: String,
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market() {
+        let json = r#"
+            {
+                "id": 27,
+                "id64": 10477373803,
+                "name": "Sol",
+                "marketId": 128106744,
+                "sId": 1,
+                "sName": "Daedalus",
+                "url": "https://www.edsm.net/en/system/stations/id/27/name/Sol",
+                "commodities": [
+                    {
+                        "id": "advancedcatalysers",
+                        "name": "Advanced Catalysers",
+                        "buyPrice": 0,
+                        "stock": 0,
+                        "sellPrice": 3234,
+                        "demand": 1520,
+                        "stockBracket": 0
+                    }
+                ]
+            }
+        "#;
+        let market: Market = serde_json::from_str(json).unwrap();
+        assert_eq!("Daedalus", market.station_name);
+        assert_eq!(3234, market.commodities[0].sell_price);
+    }
+}
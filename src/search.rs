@@ -0,0 +1,230 @@
+use crate::api::Show;
+use crate::System;
+use elite_journal::prelude::Coordinate;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+/// How closely a system's name matches a [`Search`], from closest to furthest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Closeness {
+    Exact,
+    CaseInsensitive,
+    /// The name starts with the query, ignoring case
+    Prefix,
+    /// The name is the given number of edits from the query, ignoring case
+    Fuzzy(usize),
+}
+
+/// A system found by a [`Search`]
+#[derive(Debug)]
+pub struct Candidate<S> {
+    pub system: S,
+    pub closeness: Closeness,
+    /// Light years from the search's reference point, if both are known
+    pub distance: Option<f64>,
+}
+
+/// A search for systems by name, ranked by how close each is to the query
+///
+/// The same search can rank systems from the API with
+/// [`Client::search`][crate::api::Client::search], or from a dump loaded with
+/// [`json`][crate::json]. EDSM only returns names starting with the query, so fuzzy matches come
+/// from local systems.
+///
+/// ```no_run
+/// use edsm::search::Search;
+///
+/// let systems = edsm::json("dumps/systemsPopulated.json");
+/// for candidate in Search::new("shinrarta").limit(5).rank(&systems) {
+///     println!("{:?} {}", candidate.closeness, candidate.system.name);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Search {
+    pub query: String,
+    /// Break ties by distance from this point
+    pub reference: Option<Coordinate>,
+    /// The most edits a fuzzy match may be from the query, by default a third of its length
+    pub max_edits: usize,
+    pub limit: Option<usize>,
+    /// What to request from the API, coordinates are needed to sort by distance
+    pub show: Show,
+}
+
+impl Search {
+    pub fn new<S: Into<String>>(query: S) -> Search {
+        let query = query.into();
+        Search {
+            max_edits: (query.chars().count() / 3).max(1),
+            query,
+            reference: None,
+            limit: None,
+            show: Show {
+                id: true,
+                coordinates: true,
+                ..Show::none()
+            },
+        }
+    }
+
+    /// Sort equally close matches by distance from the given point
+    pub fn near(mut self, reference: Coordinate) -> Search {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Return at most this many candidates
+    pub fn limit(mut self, limit: usize) -> Search {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Allow fuzzy matches at most this many edits from the query
+    pub fn max_edits(mut self, max_edits: usize) -> Search {
+        self.max_edits = max_edits;
+        self
+    }
+
+    /// How closely the given name matches, or `None` if it doesn't
+    pub fn closeness(&self, name: &str) -> Option<Closeness> {
+        if name == self.query {
+            return Some(Closeness::Exact);
+        }
+        let query = self.query.to_lowercase();
+        let name = name.to_lowercase();
+        if name == query {
+            Some(Closeness::CaseInsensitive)
+        } else if name.starts_with(&query) {
+            Some(Closeness::Prefix)
+        } else {
+            let edits = levenshtein(&query, &name);
+            if edits <= self.max_edits {
+                Some(Closeness::Fuzzy(edits))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Rank the matching systems, closest first
+    ///
+    /// Accepts owned systems or references, so a loaded dump can be searched without copying.
+    pub fn rank<I, S>(&self, systems: I) -> Vec<Candidate<S>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Borrow<System>,
+    {
+        let mut candidates: Vec<_> = systems
+            .into_iter()
+            .filter_map(|system| {
+                let s = system.borrow();
+                let closeness = self.closeness(&s.name)?;
+                let distance = match (&self.reference, &s.coords) {
+                    (Some(a), Some(b)) => Some(distance(a, b)),
+                    _ => None,
+                };
+                Some(Candidate {
+                    system,
+                    closeness,
+                    distance,
+                })
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            a.closeness
+                .cmp(&b.closeness)
+                .then_with(|| match (a.distance, b.distance) {
+                    (Some(a), Some(b)) => {
+                        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                    }
+                    // Systems with a known distance first.
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
+                .then_with(|| {
+                    a.system.borrow().name.cmp(&b.system.borrow().name)
+                })
+        });
+        if let Some(limit) = self.limit {
+            candidates.truncate(limit);
+        }
+        candidates
+    }
+}
+
+fn distance(a: &Coordinate, b: &Coordinate) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+// The number of single character insertions, deletions or substitutions between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(name: &str, x: f64) -> System {
+        let json = format!(
+            r#"{{ "name": "{}", "coords": {{ "x": {}, "y": 0, "z": 0 }} }}"#,
+            name, x
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(0, levenshtein("sol", "sol"));
+        assert_eq!(1, levenshtein("sol", "sal"));
+        assert_eq!(3, levenshtein("", "sol"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+    }
+
+    #[test]
+    fn rank() {
+        let systems = vec![
+            system("Shinrarta Dezhra", 55.7),
+            system("Sol", 0.),
+            system("SOL", 10.),
+            system("Solati", 80.),
+            system("Sola", 20.),
+            system("Sal", 5.),
+            system("Achenar", 67.5),
+        ];
+        let search = Search::new("Sol");
+        let names: Vec<_> = search
+            .rank(&systems)
+            .iter()
+            .map(|c| c.system.name.as_str())
+            .collect();
+        assert_eq!(vec!["Sol", "SOL", "Sola", "Solati", "Sal"], names);
+
+        let reference = Coordinate {
+            x: 100.,
+            y: 0.,
+            z: 0.,
+        };
+        let candidates = Search::new("sol").near(reference).rank(&systems);
+        assert_eq!("SOL", candidates[0].system.name);
+        assert_eq!(Closeness::CaseInsensitive, candidates[0].closeness);
+        assert_eq!("Solati", candidates[2].system.name);
+        assert_eq!(Some(20.), candidates[2].distance);
+
+        let limited = Search::new("sol").limit(1).rank(systems);
+        assert_eq!(1, limited.len());
+    }
+}
@@ -1,5 +1,5 @@
 use chrono::NaiveDateTime;
-use serde::de::{self, MapAccess, Visitor};
+use serde::de::{self, DeserializeOwned, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::fmt;
 use std::marker::PhantomData;
@@ -74,3 +74,38 @@ where
 
     deserializer.deserialize_str(NaiveDateTimeVisitor)
 }
+
+pub fn optional_space_seperated_datetime<'de, D>(
+    deserializer: D,
+) -> Result<Option<NaiveDateTime>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(
+        #[serde(deserialize_with = "space_seperated_datetime")] NaiveDateTime,
+    );
+
+    let wrapper = Option::<Wrapper>::deserialize(deserializer)?;
+    Ok(wrapper.map(|Wrapper(t)| t))
+}
+
+// One of `elite_journal`'s enums, which EDSM sometimes has values for that it doesn't know, like
+// the `Fleet Carrier` government and economy. Those are read as `Carrier`, and any other unknown
+// value as `None`, rather than failing the whole record.
+pub fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| {
+        let value = match value.as_str() {
+            "Fleet Carrier" => "Carrier",
+            value => value,
+        };
+        let deserializer =
+            de::value::StrDeserializer::<de::value::Error>::new(value);
+        T::deserialize(deserializer).ok()
+    }))
+}
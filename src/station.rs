@@ -0,0 +1,132 @@
+use crate::serde_utils::{lenient, optional_space_seperated_datetime};
+use crate::ControllingFaction;
+use chrono::NaiveDateTime;
+use elite_journal::prelude::{Allegiance, Economy, Government};
+use serde::Deserialize;
+
+/// EDSM's representation of a station, outpost, settlement or fleet carrier
+#[derive(Deserialize, Debug)]
+pub struct Station {
+    pub id: u64,
+    /// The id used for the station's market, shipyard and outfitting
+    #[serde(rename = "marketId")]
+    pub market_id: Option<u64>,
+    /// The kind of station, like `Coriolis Starport` or `Fleet Carrier`
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    pub name: String,
+    /// Distance from the arrival point in light seconds
+    #[serde(rename = "distanceToArrival")]
+    pub distance_to_arrival: Option<f64>,
+
+    // Fleet carriers and newer stations use values `elite_journal` may not know.
+    #[serde(deserialize_with = "lenient")]
+    #[serde(default)]
+    pub allegiance: Option<Allegiance>,
+    #[serde(deserialize_with = "lenient")]
+    #[serde(default)]
+    pub government: Option<Government>,
+    #[serde(deserialize_with = "lenient")]
+    #[serde(default)]
+    pub economy: Option<Economy>,
+    #[serde(rename = "secondEconomy")]
+    #[serde(deserialize_with = "lenient")]
+    #[serde(default)]
+    pub second_economy: Option<Economy>,
+
+    #[serde(rename = "haveMarket")]
+    pub have_market: bool,
+    #[serde(rename = "haveShipyard")]
+    pub have_shipyard: bool,
+    #[serde(rename = "haveOutfitting")]
+    pub have_outfitting: bool,
+    /// Services besides the market, shipyard and outfitting, like `Refuel` or `Black Market`
+    #[serde(rename = "otherServices")]
+    #[serde(default)]
+    pub other_services: Vec<String>,
+
+    #[serde(rename = "controllingFaction")]
+    pub controlling_faction: Option<ControllingFaction>,
+    #[serde(rename = "updateTime")]
+    pub updated_at: Option<UpdateTime>,
+}
+
+/// When each part of a [Station] was last updated
+#[derive(Deserialize, Debug)]
+pub struct UpdateTime {
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub information: Option<NaiveDateTime>,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub market: Option<NaiveDateTime>,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub shipyard: Option<NaiveDateTime>,
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub outfitting: Option<NaiveDateTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn station() {
+        let json = r#"
+            {
+                "id": 1,
+                "marketId": 128106744,
+                "type": "Ocellus Starport",
+                "name": "Daedalus",
+                "distanceToArrival": 199.13,
+                "allegiance": "Federation",
+                "government": "Democracy",
+                "economy": "Industrial",
+                "secondEconomy": null,
+                "haveMarket": true,
+                "haveShipyard": true,
+                "haveOutfitting": false,
+                "otherServices": ["Black Market", "Refuel"],
+                "controllingFaction": { "id": 1, "name": "Mother Gaia" },
+                "updateTime": {
+                    "information": "2020-08-14 10:22:31",
+                    "market": "2020-08-14 10:22:31",
+                    "shipyard": null,
+                    "outfitting": null
+                }
+            }
+        "#;
+        let station: Station = serde_json::from_str(json).unwrap();
+        assert_eq!(Some(128106744), station.market_id);
+        assert!(station.second_economy.is_none());
+        assert_eq!(2, station.other_services.len());
+        let updated_at = station.updated_at.unwrap();
+        assert!(updated_at.market.is_some());
+        assert!(updated_at.shipyard.is_none());
+    }
+
+    #[test]
+    fn fleet_carrier() {
+        let json = r#"
+            {
+                "id": 67471,
+                "marketId": 3700735232,
+                "type": "Fleet Carrier",
+                "name": "XLH-B9X",
+                "allegiance": "Independent",
+                "government": "Fleet Carrier",
+                "economy": "Fleet Carrier",
+                "secondEconomy": "Not An Economy",
+                "haveMarket": false,
+                "haveShipyard": false,
+                "haveOutfitting": false
+            }
+        "#;
+        let station: Station = serde_json::from_str(json).unwrap();
+        assert!(matches!(station.government, Some(Government::Carrier)));
+        assert!(matches!(station.economy, Some(Economy::Carrier)));
+        assert!(station.second_economy.is_none());
+    }
+}
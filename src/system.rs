@@ -1,50 +1,59 @@
-use std::collections::HashMap;
-// use chrono::naive::NaiveDateTime;
-use crate::{Body, ControllingFaction, Faction, State};
+use crate::serde_utils::optional_space_seperated_datetime;
+use crate::{Body, ControllingFaction, Faction, State, Station};
+use chrono::naive::NaiveDateTime;
 use elite_journal::de::*;
 use elite_journal::prelude::{
     Allegiance, Coordinate, Economy, Government, Security,
 };
 use serde::Deserialize;
-// use crate::serde_utils;
+use std::collections::HashMap;
 
 /// EDSM's representation of a solar system
 #[derive(Deserialize, Debug)]
 pub struct System {
     pub name: String,
+    /// Filled by [`Show::id`][crate::api::Show::id]
     pub id: Option<u64>,
+    /// Filled by [`Show::id`][crate::api::Show::id]
     pub id64: Option<u64>,
 
-    // TODO: Support Optional date.
-    // #[serde(deserialize_with = "serde_utils::space_seperated_datetime")]
-    // pub date: Option<NaiveDateTime>,
-
-    // from showCoords API parameter.
+    /// When the system was added to EDSM
+    #[serde(deserialize_with = "optional_space_seperated_datetime")]
+    #[serde(default)]
+    pub date: Option<NaiveDateTime>,
+    /// Filled by [`Show::coordinates`][crate::api::Show::coordinates]
     pub coords: Option<Coordinate>,
+    /// Filled by [`Show::coordinates`][crate::api::Show::coordinates]
     #[serde(rename = "coordsLocked")]
     pub coords_locked: Option<bool>,
 
-    // from showInformation API parameter.
+    /// Filled by [`Show::information`][crate::api::Show::information]
     #[serde(flatten, deserialize_with = "deserialize_information")]
     pub information: Information,
 
-    // from showPermit API parameter.
+    /// Filled by [`Show::permit`][crate::api::Show::permit]
     #[serde(rename = "requirePermit")]
     pub require_permit: Option<bool>,
+    /// Filled by [`Show::permit`][crate::api::Show::permit]
     #[serde(rename = "permitName")]
     pub permit_name: Option<String>,
 
-    // NOTE: Skipping this for now, use bodies to access if needed. Use the
-    // showPrimaryStar API parameter when needed.
-    // pub primary_star: Option<PrimaryStar>,
+    /// Filled by [`Show::primary_star`][crate::api::Show::primary_star]
+    ///
+    /// Use the system's bodies for more details about the star.
+    #[serde(rename = "primaryStar")]
+    #[serde(deserialize_with = "empty_is_none")]
+    #[serde(default)]
+    pub primary_star: Option<PrimaryStar>,
 
     // from system bodies API.
     #[serde(rename = "bodyCount")]
     pub body_count: Option<u64>,
     pub bodies: Option<Vec<Body>>,
 
-    // TODO
-    // pub stations: Option<Vec<Station>>,
+    // from system stations API.
+    pub stations: Option<Vec<Station>>,
+
     pub factions: Option<Vec<Faction>>,
     #[serde(rename = "controllingFaction")]
     pub controlling_faction: Option<ControllingFaction>,
@@ -56,6 +65,99 @@ pub struct System {
     pub traffic_breakdown: Option<HashMap<String, u64>>,
 }
 
+impl System {
+    /// Combine a partial record of the same system into this one
+    ///
+    /// Every field `other` has replaces ours regardless of age, since EDSM doesn't say when a
+    /// system's information or coordinates were last updated, so only merge records which are
+    /// at least as new as this one. Bodies, stations and factions do carry timestamps, so
+    /// they're matched up by id, keeping whichever was updated most recently. Returns `false`,
+    /// leaving this system unchanged, when the ids (or without ids, the names) show `other` is
+    /// a different system.
+    pub fn merge(&mut self, other: System) -> bool {
+        let same = match ((self.id, self.id64), (other.id, other.id64)) {
+            ((Some(a), _), (Some(b), _)) => a == b,
+            ((_, Some(a)), (_, Some(b))) => a == b,
+            _ => self.name.eq_ignore_ascii_case(&other.name),
+        };
+        if !same {
+            return false;
+        }
+
+        self.name = other.name;
+        replace(&mut self.id, other.id);
+        replace(&mut self.id64, other.id64);
+        replace(&mut self.date, other.date);
+        replace(&mut self.coords, other.coords);
+        replace(&mut self.coords_locked, other.coords_locked);
+        self.information.merge(other.information);
+        replace(&mut self.require_permit, other.require_permit);
+        replace(&mut self.permit_name, other.permit_name);
+        replace(&mut self.primary_star, other.primary_star);
+        replace(&mut self.body_count, other.body_count);
+        merge_by_id(
+            &mut self.bodies,
+            other.bodies,
+            |b| b.id,
+            |b| Some(b.updated_at),
+        );
+        merge_by_id(
+            &mut self.stations,
+            other.stations,
+            |s| s.id,
+            |s| s.updated_at.as_ref().and_then(|u| u.information),
+        );
+        merge_by_id(
+            &mut self.factions,
+            other.factions,
+            |f| f.id,
+            |f| f.last_updated,
+        );
+        replace(&mut self.controlling_faction, other.controlling_faction);
+        replace(&mut self.deaths, other.deaths);
+        replace(&mut self.traffic, other.traffic);
+        replace(&mut self.traffic_breakdown, other.traffic_breakdown);
+        true
+    }
+}
+
+// Take the other value, if it has one.
+fn replace<T>(ours: &mut Option<T>, theirs: Option<T>) {
+    if theirs.is_some() {
+        *ours = theirs;
+    }
+}
+
+// Merge two lists of records, replacing ours unless theirs is known to be older.
+fn merge_by_id<T, U>(
+    ours: &mut Option<Vec<T>>,
+    theirs: Option<Vec<T>>,
+    id: impl Fn(&T) -> u64,
+    updated: impl Fn(&T) -> Option<U>,
+) where
+    U: PartialOrd,
+{
+    let theirs = match theirs {
+        Some(theirs) => theirs,
+        None => return,
+    };
+    let ours = ours.get_or_insert_with(Vec::new);
+    for record in theirs {
+        match ours.iter_mut().find(|r| id(r) == id(&record)) {
+            Some(existing) => {
+                let older = match (updated(&record), updated(existing)) {
+                    (Some(theirs), Some(ours)) => theirs < ours,
+                    _ => false,
+                };
+                if !older {
+                    *existing = record;
+                }
+            }
+            None => ours.push(record),
+        }
+    }
+}
+
 fn deserialize_information<'de, T, D>(
     deserializer: D,
 ) -> std::result::Result<T, D::Error>
@@ -111,6 +213,93 @@ pub struct Information {
     pub reserve: Option<String>,
 }
 
+impl Information {
+    fn merge(&mut self, other: Information) {
+        replace(&mut self.allegiance, other.allegiance);
+        replace(&mut self.government, other.government);
+        replace(&mut self.faction, other.faction);
+        replace(&mut self.state, other.state);
+        replace(&mut self.population, other.population);
+        replace(&mut self.security, other.security);
+        replace(&mut self.economy, other.economy);
+        replace(&mut self.second_economy, other.second_economy);
+        replace(&mut self.reserve, other.reserve);
+    }
+}
+
+/// The main star of a [System]
+#[derive(Deserialize, Debug)]
+pub struct PrimaryStar {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub name: String,
+    #[serde(rename = "isScoopable")]
+    pub is_scoopable: bool,
+}
+
+// EDSM sends an empty array instead of a primary star when it doesn't know it.
+fn empty_is_none<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<PrimaryStar>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Either {
+        Star(PrimaryStar),
+        Empty([(); 0]),
+    }
+
+    Ok(match Option::<Either>::deserialize(deserializer)? {
+        Some(Either::Star(star)) => Some(star),
+        Some(Either::Empty(_)) | None => None,
+    })
+}
+
+/// What a [System] is worth to explorers, according to EDSM
+#[derive(Deserialize, Debug)]
+pub struct EstimatedValue {
+    pub id: u64,
+    pub id64: Option<u64>,
+    pub name: String,
+    pub url: Option<String>,
+    /// Credits for scanning every body
+    #[serde(rename = "estimatedValue")]
+    pub estimated_value: u64,
+    /// Credits for scanning and mapping every body
+    #[serde(rename = "estimatedValueMapped")]
+    pub estimated_value_mapped: u64,
+    #[serde(rename = "valuableBodies")]
+    pub valuable_bodies: Vec<ValuableBody>,
+}
+
+/// A [Body] worth a detailed surface scan
+#[derive(Deserialize, Debug)]
+pub struct ValuableBody {
+    /// The EDSM id, matching [`Body::id`]
+    #[serde(rename = "bodyId")]
+    pub body_id: u64,
+    #[serde(rename = "bodyName")]
+    pub body_name: String,
+    /// Distance from the arrival point in light seconds
+    pub distance: u64,
+    /// Credits for scanning and mapping this body
+    #[serde(rename = "valueMax")]
+    pub value_max: u64,
+}
+
+impl ValuableBody {
+    /// Find this body among a system's [bodies][System::bodies]
+    pub fn body_in<'a>(&self, system: &'a System) -> Option<&'a Body> {
+        system
+            .bodies
+            .as_ref()?
+            .iter()
+            .find(|b| b.id == self.body_id)
+    }
+}
+
 /// Statistics for how much ship traffic a [System] gets.
 #[derive(Deserialize, Debug)]
 pub struct Statistic {
@@ -118,3 +307,104 @@ pub struct Statistic {
     pub week: u64,
     pub day: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faction(id: u64, influence: f64, last_updated: u64) -> String {
+        format!(
+            r#"{{
+                "id": {}, "name": "Faction {}", "influence": {},
+                "activeStates": [], "recoveringStates": [], "pendingStates": [],
+                "isPlayer": false, "lastUpdated": {}
+            }}"#,
+            id, id, influence, last_updated
+        )
+    }
+
+    #[test]
+    fn merge() {
+        let mut system: System = serde_json::from_str(
+            r#"{ "id": 27, "name": "Sol", "population": 22780919531 }"#,
+        )
+        .unwrap();
+        let json = format!(
+            r#"{{ "id": 27, "name": "Sol", "factions": [{}, {}] }}"#,
+            faction(1, 0.6, 200),
+            faction(2, 0.4, 200)
+        );
+        assert!(system.merge(serde_json::from_str(&json).unwrap()));
+        let json = format!(
+            r#"{{
+                "id": 27, "name": "Sol",
+                "traffic": {{ "total": 10, "week": 5, "day": 1 }},
+                "factions": [{}, {}]
+            }}"#,
+            faction(1, 0.1, 100),
+            faction(2, 0.3, 300)
+        );
+        assert!(system.merge(serde_json::from_str(&json).unwrap()));
+
+        assert_eq!(Some(22780919531), system.information.population);
+        assert_eq!(Some(5), system.traffic.as_ref().map(|t| t.week));
+        let factions = system.factions.as_ref().unwrap();
+        assert_eq!(0.6, factions[0].influence);
+        assert_eq!(0.3, factions[1].influence);
+
+        // Without timestamps the argument always wins.
+        let json = r#"{ "id": 27, "name": "Sol", "population": 1 }"#;
+        assert!(system.merge(serde_json::from_str(json).unwrap()));
+        assert_eq!(Some(1), system.information.population);
+
+        let other: System =
+            serde_json::from_str(r#"{ "id": 28, "name": "Sol" }"#).unwrap();
+        assert!(!system.merge(other));
+    }
+
+    #[test]
+    fn estimated_value() {
+        let json = r#"
+            {
+                "id": 27,
+                "id64": 10477373803,
+                "name": "Sol",
+                "url": "https://www.edsm.net/en/system/bodies/id/27/name/Sol",
+                "estimatedValue": 1000,
+                "estimatedValueMapped": 3000,
+                "valuableBodies": [
+                    {
+                        "bodyId": 6,
+                        "bodyName": "Earth",
+                        "distance": 499,
+                        "valueMax": 2500
+                    }
+                ]
+            }
+        "#;
+        let value: EstimatedValue = serde_json::from_str(json).unwrap();
+        assert_eq!(3000, value.estimated_value_mapped);
+        assert_eq!(6, value.valuable_bodies[0].body_id);
+    }
+
+    #[test]
+    fn primary_star() {
+        let known = r#"
+            {
+                "name": "Sol",
+                "primaryStar": {
+                    "type": "G (White-Yellow) Star",
+                    "name": "Sol",
+                    "isScoopable": true
+                }
+            }
+        "#;
+        let system: System = serde_json::from_str(known).unwrap();
+        assert!(system.primary_star.unwrap().is_scoopable);
+        let unknown = r#"
+            { "name": "Nowhere", "primaryStar": [] }
+        "#;
+        let system: System = serde_json::from_str(unknown).unwrap();
+        assert!(system.primary_star.is_none());
+    }
+}
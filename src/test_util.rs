@@ -0,0 +1,413 @@
+use crate::api::{key_hash, ClientBuilder};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Set to record missing fixtures with [`MockServer::from_env`]
+pub const RECORD_VAR: &str = "EDSM_RECORD";
+
+// What API keys are replaced with in fixtures.
+const REDACTED: &str = "REDACTED";
+
+/// A recorded response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Recorded responses, keyed by request
+///
+/// Keys are the method and the path and parameters relative to the base URL, like
+/// `GET api-v1/system?systemName=Sol`. For `POST` requests the form is used as the parameters,
+/// and any `apiKey` is redacted so fixtures are safe to commit.
+///
+/// On disk each fixture is a file holding its key, its status and then its body, each starting
+/// on a new line.
+#[derive(Debug, Default)]
+pub struct Fixtures {
+    dir: Option<PathBuf>,
+    responses: HashMap<String, Fixture>,
+}
+
+impl Fixtures {
+    /// Fixtures kept only in memory
+    pub fn new() -> Fixtures {
+        Fixtures::default()
+    }
+
+    /// Load every fixture in a directory, where recorded fixtures will be saved too
+    pub fn load<P: Into<PathBuf>>(dir: P) -> io::Result<Fixtures> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let mut responses = HashMap::new();
+        for file in fs::read_dir(&dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)?;
+            let mut parts = contents.splitn(3, '\n');
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid fixture: {}", path.display()),
+                )
+            };
+            let key = parts.next().ok_or_else(invalid)?;
+            let status = parts
+                .next()
+                .and_then(|s| s.trim().parse().ok())
+                .ok_or_else(invalid)?;
+            let body = parts.next().unwrap_or("").into();
+            responses.insert(key.into(), Fixture { status, body });
+        }
+        Ok(Fixtures {
+            dir: Some(dir),
+            responses,
+        })
+    }
+
+    /// Add a successful response, the target is relative to the base URL
+    ///
+    /// ```
+    /// use edsm::test_util::Fixtures;
+    ///
+    /// let mut fixtures = Fixtures::new();
+    /// fixtures.insert("GET", "api-v1/system?systemName=Sol", r#"{ "name": "Sol" }"#);
+    /// ```
+    pub fn insert<B: Into<String>>(
+        &mut self,
+        method: &str,
+        target: &str,
+        body: B,
+    ) {
+        let fixture = Fixture {
+            status: 200,
+            body: body.into(),
+        };
+        self.responses.insert(key(method, target, ""), fixture);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Fixture> {
+        self.responses.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    // Keep a recorded response, saving it when loaded from a directory.
+    fn record(&mut self, key: String, fixture: Fixture) -> io::Result<()> {
+        if let Some(dir) = &self.dir {
+            let contents =
+                format!("{}\n{}\n{}", key, fixture.status, fixture.body);
+            fs::write(dir.join(file_name(&key)), contents)?;
+        }
+        self.responses.insert(key, fixture);
+        Ok(())
+    }
+}
+
+// The fixture key of a request, with the body used for a form's parameters.
+fn key(method: &str, target: &str, body: &str) -> String {
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (target, ""),
+    };
+    let params = if method == "POST" { body } else { query };
+    let redacted: String = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form_urlencoded::parse(params.as_bytes()).map(
+            |(k, v)| {
+                let v = if k == "apiKey" { REDACTED.into() } else { v };
+                (k, v)
+            },
+        ))
+        .finish();
+    let path = path.trim_start_matches('/');
+    if redacted.is_empty() {
+        format!("{} {}", method, path)
+    } else {
+        format!("{} {}?{}", method, path, redacted)
+    }
+}
+
+// A readable file name which is unique to the key.
+fn file_name(key: &str) -> String {
+    let hash = key_hash(key);
+    let path = key.split([' ', '?']).nth(1).unwrap_or("");
+    format!("{}-{:08x}.json", path.replace('/', "_"), hash as u32)
+}
+
+/// A local stand-in for EDSM, serving [`Fixtures`]
+///
+/// Requests without a fixture get a `404 Not Found`. In record mode they're forwarded to EDSM
+/// instead, and the responses saved, so a test suite's fixtures can be refreshed by running it
+/// once online. Only successful responses are saved, failures are passed on to the client and
+/// reported on stderr, so rerun to record them. The server stops when dropped.
+///
+/// ```no_run
+/// use edsm::api::Show;
+/// use edsm::test_util::MockServer;
+///
+/// let server = MockServer::from_env("tests/fixtures").unwrap();
+/// let client = server.client().build().unwrap();
+/// let sol = client.system("Sol", Show::default()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    fixtures: Arc<Mutex<Fixtures>>,
+    requests: Arc<Mutex<Vec<String>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    /// Serve only the given fixtures
+    pub fn replay(fixtures: Fixtures) -> io::Result<MockServer> {
+        MockServer::start(fixtures, None)
+    }
+
+    /// Serve the given fixtures, recording any others from the upstream server
+    pub fn record(fixtures: Fixtures, upstream: Url) -> io::Result<MockServer> {
+        MockServer::start(fixtures, Some(upstream))
+    }
+
+    /// Replay the fixtures in a directory, or record from EDSM if [`RECORD_VAR`] is set
+    pub fn from_env<P: Into<PathBuf>>(dir: P) -> io::Result<MockServer> {
+        let fixtures = Fixtures::load(dir)?;
+        if env::var_os(RECORD_VAR).is_some() {
+            let upstream = crate::api::DEFAULT_URL.parse().expect("valid URL");
+            MockServer::record(fixtures, upstream)
+        } else {
+            MockServer::replay(fixtures)
+        }
+    }
+
+    fn start(
+        fixtures: Fixtures,
+        upstream: Option<Url>,
+    ) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let server = MockServer {
+            addr: listener.local_addr()?,
+            fixtures: Arc::new(Mutex::new(fixtures)),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let fixtures = server.fixtures.clone();
+        let requests = server.requests.clone();
+        let stopped = server.stopped.clone();
+        thread::spawn(move || {
+            let http =
+                upstream.as_ref().map(|_| reqwest::blocking::Client::new());
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let upstream = upstream.as_ref().zip(http.as_ref());
+                    // A broken connection only affects its own request.
+                    let _ = serve(stream, &fixtures, &requests, upstream);
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    /// The base URL to give a client
+    pub fn url(&self) -> Url {
+        format!("http://{}", self.addr).parse().expect("valid URL")
+    }
+
+    /// A client builder already pointed at this server
+    pub fn client(&self) -> ClientBuilder {
+        ClientBuilder::new().base_url(self.url())
+    }
+
+    /// The keys of every request received so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().expect("requests lock").clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the server up so it sees it's stopped.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+// Answer a single HTTP/1.1 request, closing the connection after.
+fn serve(
+    stream: TcpStream,
+    fixtures: &Mutex<Fixtures>,
+    requests: &Mutex<Vec<String>>,
+    upstream: Option<(&Url, &reqwest::blocking::Client)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_owned();
+    let target = parts.next().unwrap_or("").to_owned();
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body);
+
+    let key = key(&method, &target, &body);
+    requests.lock().expect("requests lock").push(key.clone());
+    let fixture = fixtures.lock().expect("fixtures lock").get(&key).cloned();
+    let fixture = match (fixture, upstream) {
+        (Some(fixture), _) => fixture,
+        (None, Some((upstream, http))) => {
+            let fixture = forward(http, upstream, &method, &target, &body);
+            // Rate limits and outages would otherwise replay forever.
+            if (200..300).contains(&fixture.status) {
+                fixtures
+                    .lock()
+                    .expect("fixtures lock")
+                    .record(key, fixture.clone())?;
+            } else {
+                eprintln!(
+                    "not recording {}: {} {}",
+                    key, fixture.status, fixture.body
+                );
+            }
+            fixture
+        }
+        (None, None) => {
+            eprintln!("no fixture for {}", key);
+            Fixture {
+                status: 404,
+                body: String::new(),
+            }
+        }
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        fixture.status,
+        reqwest::StatusCode::from_u16(fixture.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or(""),
+        fixture.body.len(),
+    )?;
+    stream.write_all(fixture.body.as_bytes())?;
+    stream.flush()
+}
+
+// Get the real response to record, failing to get one is a `502 Bad Gateway`.
+fn forward(
+    http: &reqwest::blocking::Client,
+    upstream: &Url,
+    method: &str,
+    target: &str,
+    body: &str,
+) -> Fixture {
+    let url = format!(
+        "{}/{}",
+        upstream.as_str().trim_end_matches('/'),
+        target.trim_start_matches('/')
+    );
+    let request = if method == "POST" {
+        http.post(&url)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(body.to_owned())
+    } else {
+        http.get(&url)
+    };
+    match request.send().and_then(|r| Ok((r.status(), r.text()?))) {
+        Ok((status, body)) => Fixture {
+            status: status.as_u16(),
+            body,
+        },
+        Err(e) => Fixture {
+            status: 502,
+            body: e.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            "GET api-v1/system?systemName=Sol",
+            key("GET", "/api-v1/system?systemName=Sol", "")
+        );
+        assert_eq!(
+            "POST api-commander-v1/get-ranks?commanderName=Jameson&apiKey=REDACTED",
+            key(
+                "POST",
+                "/api-commander-v1/get-ranks",
+                "commanderName=Jameson&apiKey=secret"
+            )
+        );
+        assert_eq!(
+            "api-v1_system-",
+            &file_name("GET api-v1/system?systemName=Sol")[..14]
+        );
+    }
+
+    #[test]
+    fn records_only_successes() {
+        let mut upstream = Fixtures::new();
+        upstream.insert("GET", "api-v1/system?systemName=Sol", "{}");
+        let upstream = MockServer::replay(upstream).unwrap();
+        let dir = env::temp_dir()
+            .join(format!("edsm-fixtures-{}", std::process::id()));
+        let fixtures = Fixtures::load(&dir).unwrap();
+        let server = MockServer::record(fixtures, upstream.url()).unwrap();
+
+        let http = reqwest::blocking::Client::new();
+        let get = |target: &str| {
+            http.get(format!("{}{}", server.url(), target))
+                .send()
+                .unwrap()
+                .status()
+                .as_u16()
+        };
+        assert_eq!(200, get("api-v1/system?systemName=Sol"));
+        assert_eq!(404, get("api-v1/system?systemName=Nowhere"));
+        assert_eq!(1, Fixtures::load(&dir).unwrap().len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,31 @@
-use edsm::api::*;
+// The assertions read better comparing lengths directly.
+#![allow(clippy::len_zero)]
+
+use edsm::api::{Client, Credentials, Error, Inventory, Show};
+use edsm::test_util::MockServer;
 use elite_journal::{system::Coordinate, Allegiance, Government};
+use std::sync::OnceLock;
+
+// Replays `tests/fixtures`, set `EDSM_RECORD` to refresh them from EDSM.
+fn client() -> Client {
+    static SERVER: OnceLock<MockServer> = OnceLock::new();
+    let server = SERVER.get_or_init(|| {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        MockServer::from_env(fixtures).unwrap()
+    });
+    server.client().build().unwrap()
+}
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
 fn test_systems() {
-    let system = systems("Sol").unwrap_or_else(|e| panic!("{}", e));
+    let system = client()
+        .systems("Sol", Show::default())
+        .unwrap_or_else(|e| panic!("{}", e));
     assert!(system.len() == 1);
 
-    let systems = systems("Soli").unwrap_or_else(|e| panic!("{}", e));
+    let systems = client()
+        .systems("Soli", Show::default())
+        .unwrap_or_else(|e| panic!("{}", e));
     assert!(systems.len() > 0);
     assert!(systems.len() > 1);
 }
@@ -16,53 +34,84 @@ mod sphere {
     use super::*;
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_sphere_default() {
-        let systems = systems_sphere("Sol", None, None)
+        let systems = client()
+            .systems_sphere("Sol", None, None, Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(1238, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_sphere_max() {
-        let systems = systems_sphere("EV Cancri", Some(100.), None)
+        let systems = client()
+            .systems_sphere("EV Cancri", Some(100.), None, Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(140, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_sphere_float_high() {
-        let systems = systems_sphere("Sol", Some(17.8), None)
+        let systems = client()
+            .systems_sphere("Sol", Some(17.8), None, Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(83, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_sphere_float_low() {
-        let systems = systems_sphere("Sol", Some(17.3), None)
+        let systems = client()
+            .systems_sphere("Sol", Some(17.3), None, Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(79, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_sphere_min_radius() {
-        let systems = systems_sphere("Sol", Some(17.3), Some(10.))
+        let systems = client()
+            .systems_sphere("Sol", Some(17.3), Some(10.), Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(66, systems.len());
     }
 
-    // TODO: WTF is EDSM doing here?
     #[test]
-    #[ignore]
-    fn test_systems_sphere_min_greater_than_max() {
-        let systems = systems_sphere("Alpha Centauri", Some(5.), Some(10.))
+    fn test_systems_sphere_coords() {
+        let sol = Coordinate {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+        };
+        let systems = client()
+            .systems_sphere(sol, Some(17.3), None, Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
-        dbg!(&systems);
-        assert_eq!(0, systems.len());
+        assert_eq!(79, systems.len());
+    }
+
+    // EDSM returns some odd results for this, so it's rejected up front.
+    #[test]
+    fn test_systems_sphere_min_greater_than_max() {
+        let result = client().systems_sphere(
+            "Alpha Centauri",
+            Some(5.),
+            Some(10.),
+            Show::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::InvalidParameter {
+                name: "minRadius",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_systems_sphere_radius_too_large() {
+        let result =
+            client().systems_sphere("Sol", Some(100.1), None, Show::default());
+        assert!(matches!(
+            result,
+            Err(Error::InvalidParameter { name: "radius", .. })
+        ));
     }
 }
 
@@ -70,44 +119,54 @@ mod cube {
     use super::*;
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_cube_default() {
-        let systems =
-            systems_cube("EV Cancri", None).unwrap_or_else(|e| panic!("{}", e));
+        let systems = client()
+            .systems_cube("EV Cancri", None, Show::default())
+            .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(107, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_cube_max() {
-        let systems = systems_cube("EV Cancri", Some(200.))
+        let systems = client()
+            .systems_cube("EV Cancri", Some(200.), Show::default())
             .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(174, systems.len());
     }
 
+    #[test]
+    fn test_systems_cube_too_large() {
+        let result = client().systems_cube("Sol", Some(200.1), Show::default());
+        assert!(matches!(
+            result,
+            Err(Error::InvalidParameter { name: "size", .. })
+        ));
+    }
+
     // NOTE: The cube API seems to be rounding the size, unlike the radius for the sphere API.
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_cube_float_high() {
-        let systems =
-            systems_cube("Sol", Some(24.)).unwrap_or_else(|e| panic!("{}", e));
+        let systems = client()
+            .systems_cube("Sol", Some(24.), Show::default())
+            .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(45, systems.len());
     }
 
     #[test]
-    #[ignore = "EDSM API returns Too Many Requests"]
     fn test_systems_cube_float_low() {
-        let systems =
-            systems_cube("Sol", Some(23.9)).unwrap_or_else(|e| panic!("{}", e));
+        let systems = client()
+            .systems_cube("Sol", Some(23.9), Show::default())
+            .unwrap_or_else(|e| panic!("{}", e));
         assert_eq!(41, systems.len());
     }
 }
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
 fn test_system() {
-    let system = system("Sol").unwrap_or_else(|e| panic!("{}", e));
+    let system = client()
+        .system("Sol", Show::default())
+        .unwrap_or_else(|e| panic!("{}", e));
     assert_eq!("Sol", system.name);
     assert_eq!(Some(27), system.id);
     assert_eq!(Some(Coordinate { x: 0.0, y: 0.0, z: 0.0 }), system.coords);
@@ -115,9 +174,43 @@ fn test_system() {
 }
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
+fn test_system_names_only() {
+    let system = client()
+        .system("Sol", Show::none())
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Sol", system.name);
+    assert!(system.id.is_none());
+    assert!(system.coords.is_none());
+    assert!(system.require_permit.is_none());
+}
+
+#[test]
+fn test_featured_systems_with_information() {
+    let result = client().featured_systems("Sol", Show::default());
+    assert!(matches!(
+        result,
+        Err(Error::InvalidParameter {
+            name: "onlyFeatured",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_system_empty_name() {
+    let result = client().system(" ", Show::default());
+    assert!(matches!(
+        result,
+        Err(Error::InvalidParameter {
+            name: "systemName",
+            ..
+        })
+    ));
+}
+
+#[test]
 fn test_bodies() {
-    let system = bodies("Sol").unwrap_or_else(|e| panic!("{}", e));
+    let system = client().bodies("Sol").unwrap_or_else(|e| panic!("{}", e));
     assert_eq!("Sol", system.name);
     assert_eq!(Some(27), system.id);
     assert!(system.coords.is_none());
@@ -128,9 +221,62 @@ fn test_bodies() {
 }
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
+fn test_estimated_value() {
+    let value = client()
+        .estimated_value("Sol")
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Sol", value.name);
+    assert!(value.estimated_value_mapped >= value.estimated_value);
+    let system = client().bodies("Sol").unwrap_or_else(|e| panic!("{}", e));
+    for valuable in &value.valuable_bodies {
+        let body = valuable.body_in(&system).expect("valuable body");
+        assert_eq!(valuable.body_name, body.name);
+    }
+}
+
+#[test]
+fn test_stations() {
+    let system = client().stations("Sol").unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Sol", system.name);
+    let stations = system.stations.expect("requested stations");
+    let daedalus = stations
+        .iter()
+        .find(|s| s.name == "Daedalus")
+        .expect("Daedalus in Sol");
+    assert!(daedalus.have_market);
+    assert!(daedalus.market_id.is_some());
+}
+
+#[test]
+fn test_market() {
+    let market = client()
+        .market(("Sol", "Daedalus"))
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Daedalus", market.station_name);
+    assert!(!market.commodities.is_empty());
+    let by_id = client()
+        .market(market.market_id)
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(market.station_id, by_id.station_id);
+}
+
+#[test]
+fn test_shipyard_and_outfitting() {
+    let shipyard = client()
+        .shipyard(("Sol", "Daedalus"))
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(!shipyard.ships.is_empty());
+    let outfitting = client()
+        .outfitting(("Sol", "Daedalus"))
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(!outfitting.modules.is_empty());
+}
+
+#[test]
 fn test_factions() {
-    let system = factions("Meliae", false).unwrap_or_else(|e| panic!("{}", e));
+    let system = client()
+        .factions("Meliae", false)
+        .unwrap_or_else(|e| panic!("{}", e));
     assert_eq!("Meliae", system.name);
     assert_eq!(Some(1062), system.id);
     let controlling_faction = system.controlling_faction.unwrap();
@@ -152,9 +298,8 @@ fn test_factions() {
 }
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
 fn test_traffic() {
-    let system = traffic("Sol").unwrap_or_else(|e| panic!("{}", e));
+    let system = client().traffic("Sol").unwrap_or_else(|e| panic!("{}", e));
     assert_eq!("Sol", system.name);
     let traffic = system.traffic.expect("requested traffic");
     assert!(traffic.total > 0);
@@ -164,12 +309,67 @@ fn test_traffic() {
 }
 
 #[test]
-#[ignore = "EDSM API returns Too Many Requests"]
 fn test_deaths() {
-    let system = deaths("Sol").unwrap_or_else(|e| panic!("{}", e));
+    let system = client().deaths("Sol").unwrap_or_else(|e| panic!("{}", e));
     assert_eq!("Sol", system.name);
     let deaths = system.deaths.expect("requested deaths");
     assert!(deaths.total > 0);
     assert!(deaths.week > 0);
     // TODO breakdown
 }
+
+#[test]
+fn test_system_with_facets() {
+    let facets = edsm::api::Facets {
+        bodies: true,
+        factions: true,
+        ..Default::default()
+    };
+    let system = client()
+        .system_with("Sol", Show::default(), facets)
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(system.information.population.is_some());
+    assert!(system.bodies.is_some());
+    assert!(system.factions.is_some());
+}
+
+#[test]
+fn test_search() {
+    let candidates = client()
+        .search(&edsm::search::Search::new("sol").limit(3))
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Sol", candidates[0].system.name);
+}
+
+#[test]
+fn test_server_status() {
+    let status = client().server_status().unwrap_or_else(|e| panic!("{}", e));
+    assert!(status.status <= 2);
+}
+
+#[test]
+fn test_discarded_events() {
+    let discard = client()
+        .discarded_events()
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(discard.contains("Music"));
+}
+
+// Recording needs a real commander, whose name ends up in the fixtures' keys.
+#[test]
+fn test_commander() {
+    let name = std::env::var("EDSM_COMMANDER").unwrap_or("Dent".into());
+    let key = std::env::var("EDSM_API_KEY").unwrap_or("not-recorded".into());
+    let client = client().with_credentials(Credentials::new(name, key));
+    let ranks = client.ranks().unwrap_or_else(|e| panic!("{}", e));
+    assert!(ranks.ranks.combat <= 8);
+    client.position().unwrap_or_else(|e| panic!("{}", e));
+    client
+        .inventory(Inventory::Materials)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+#[test]
+fn test_commander_without_credentials() {
+    assert!(matches!(client().position(), Err(Error::Unauthenticated)));
+}
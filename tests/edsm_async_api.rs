@@ -0,0 +1,36 @@
+#![cfg(feature = "async")]
+
+use edsm::api::{AsyncClient, Show};
+use edsm::test_util::MockServer;
+
+// Replays `tests/fixtures`, set `EDSM_RECORD` to refresh them from EDSM.
+fn server() -> MockServer {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    MockServer::from_env(fixtures).unwrap()
+}
+
+fn client(server: &MockServer) -> AsyncClient {
+    server.client().build_async().unwrap()
+}
+
+#[tokio::test]
+async fn test_system() {
+    let server = server();
+    let system = client(&server)
+        .system("Sol", Show::default())
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Sol", system.name);
+    assert_eq!(Some(27), system.id);
+}
+
+#[tokio::test]
+async fn test_factions() {
+    let server = server();
+    let system = client(&server)
+        .factions("Meliae", false)
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Meliae", system.name);
+    assert!(system.factions.is_some());
+}
@@ -1,4 +1,6 @@
+use edsm::dump::{self, Reader};
 use edsm::json;
+use elite_journal::prelude::{Economy, Government};
 
 #[test]
 fn systems_without_coordinates() {
@@ -10,6 +12,9 @@ fn systems_without_coordinates() {
 fn systems_with_coordinates() {
     let systems = json("tests/systemsWithCoordinates.json");
     assert!(!systems.is_empty());
+    for system in systems {
+        assert!(system.date.is_some());
+    }
 }
 
 #[test]
@@ -20,3 +25,67 @@ fn systems_populated() {
         assert!(system.information.state.is_some());
     }
 }
+
+#[test]
+fn systems_streamed() {
+    let reader: Reader<_> =
+        Reader::open("tests/systemsWithCoordinates.json").unwrap();
+    let systems: Result<Vec<_>, _> = reader.collect();
+    assert_eq!(
+        json("tests/systemsWithCoordinates.json").len(),
+        systems.unwrap().len()
+    );
+}
+
+#[test]
+fn bodies() {
+    let bodies: Vec<_> = dump::bodies("tests/bodies.json")
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert!(!bodies.is_empty());
+    assert_eq!("Rabastyane", bodies[0].system_name);
+    assert_eq!("Rabastyane", bodies[0].body.name);
+}
+
+#[test]
+fn stations() {
+    let stations: Vec<_> = dump::stations("tests/stations.json")
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert!(!stations.is_empty());
+    assert_eq!("Locke's Claim", stations[0].station.name);
+    assert_eq!(1, stations[0].commodities.as_ref().unwrap().len());
+    assert!(stations.iter().any(|s| s.body.is_some()));
+
+    let carrier = stations
+        .iter()
+        .find(|s| s.station.ty.as_deref() == Some("Fleet Carrier"))
+        .expect("a fleet carrier");
+    assert_eq!(Some(Government::Carrier), carrier.station.government);
+    assert_eq!(Some(Economy::Carrier), carrier.station.economy);
+}
+
+#[test]
+fn power_play() {
+    for system in dump::power_play("tests/powerPlay.json").unwrap() {
+        let system = system.unwrap();
+        assert!(!system.power.is_empty());
+        assert!(system.coords.is_some());
+    }
+}
+
+#[test]
+fn codex() {
+    for entry in dump::codex("tests/codex.json").unwrap() {
+        assert!(entry.unwrap().reported_on.is_some());
+    }
+}
+
+#[test]
+fn systems_populated_streamed() {
+    for system in dump::systems("tests/systemsPopulated.json").unwrap() {
+        assert!(system.unwrap().stations.is_some());
+    }
+}
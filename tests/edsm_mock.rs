@@ -0,0 +1,88 @@
+use edsm::api::{Client, Credentials, Error, Show};
+use edsm::test_util::{Fixtures, MockServer};
+
+fn server() -> MockServer {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    MockServer::replay(Fixtures::load(fixtures).unwrap()).unwrap()
+}
+
+fn client(server: &MockServer) -> Client {
+    server.client().build().unwrap()
+}
+
+#[test]
+fn test_system() {
+    let server = server();
+    let sol = client(&server)
+        .system("Sol", Show::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(Some(27), sol.id);
+    assert_eq!(Some(22780919531), sol.information.population);
+    assert_eq!(1, server.requests().len());
+}
+
+#[test]
+fn test_system_not_found() {
+    let server = server();
+    let result = client(&server).system("Nowhere", Show::default());
+    assert!(matches!(result, Err(Error::NotFound(name)) if name == "Nowhere"));
+}
+
+#[test]
+fn test_missing_fixture() {
+    let server = server();
+    let result = client(&server).system("Achenar", Show::default());
+    assert!(matches!(result, Err(Error::Edsm(status)) if status == 404));
+}
+
+#[test]
+fn test_rate_limited() {
+    let server = server();
+    let result = client(&server).traffic("Achenar");
+    assert!(matches!(result, Err(Error::RateLimited { .. })));
+}
+
+#[test]
+fn test_server_status() {
+    let server = server();
+    let status = client(&server)
+        .server_status()
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert!(status.is_up());
+}
+
+#[test]
+fn test_ranks() {
+    let server = server();
+    let client = client(&server)
+        .with_credentials(Credentials::new("Jameson", "not-in-the-fixture"));
+    let ranks = client.ranks().unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!("Elite", ranks.names.combat);
+}
+
+#[test]
+fn test_inserted_fixture() {
+    let mut fixtures = Fixtures::new();
+    fixtures.insert(
+        "GET",
+        "api-system-v1/deaths?systemName=Sol",
+        r#"{ "id": 27, "name": "Sol", "deaths": { "total": 3, "week": 2, "day": 1 } }"#,
+    );
+    let server = MockServer::replay(fixtures).unwrap();
+    let sol = client(&server)
+        .deaths("Sol")
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(Some(3), sol.deaths.map(|d| d.total));
+}
+
+#[test]
+fn test_position_not_public() {
+    let server = server();
+    let client = client(&server)
+        .with_credentials(Credentials::new("Jameson", "not-in-the-fixture"));
+    let result = client.commander_system(Show::default());
+    assert!(matches!(
+        result,
+        Err(Error::PositionNotPublic(name)) if name == "Jameson"
+    ));
+}